@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub struct Player;
@@ -12,7 +13,45 @@ pub struct Velocity(pub Vec3);
 #[derive(Component, Default)]
 pub struct Grounded(pub bool);
 
-#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+/// Horizontal shove layered over a body's own movement, so systems that set
+/// `Velocity` every frame don't cancel it. `apply_physics` moves the body by
+/// it and lets it die away.
+#[derive(Component, Deref, DerefMut, Default)]
+pub struct Knockback(pub Vec3);
+
+/// Seconds the player can't be knocked back again, so a crowd of mobs can't
+/// juggle them.
+#[derive(Component, Default)]
+pub struct IFrames(pub f32);
+
+/// Collision half-extents used by `apply_physics`.
+#[derive(Component, Clone, Copy)]
+pub struct CollisionBox {
+    pub half_width: f32,
+    pub half_height: f32,
+}
+
+impl CollisionBox {
+    pub const PLAYER: Self = Self {
+        half_width: 0.3,
+        half_height: 0.9,
+    };
+
+    pub fn cube(size: f32) -> Self {
+        Self {
+            half_width: size / 2.0,
+            half_height: size / 2.0,
+        }
+    }
+}
+
+impl Default for CollisionBox {
+    fn default() -> Self {
+        Self::PLAYER
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum BlockType {
     Grass,
@@ -22,6 +61,40 @@ pub enum BlockType {
     Leaves,
 }
 
+impl BlockType {
+    /// Name used in the shared world file.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            BlockType::Grass => "grass",
+            BlockType::Dirt => "dirt",
+            BlockType::Stone => "stone",
+            BlockType::Wood => "wood",
+            BlockType::Leaves => "leaves",
+        }
+    }
+
+    /// Seconds of holding left click to break this block.
+    pub fn hardness(self) -> f32 {
+        match self {
+            BlockType::Leaves => 0.2,
+            BlockType::Grass | BlockType::Dirt => 0.3,
+            BlockType::Wood => 0.6,
+            BlockType::Stone => 1.2,
+        }
+    }
+
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        match name {
+            "grass" => Some(BlockType::Grass),
+            "dirt" => Some(BlockType::Dirt),
+            "stone" => Some(BlockType::Stone),
+            "wood" => Some(BlockType::Wood),
+            "leaves" => Some(BlockType::Leaves),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Mob;
 
@@ -31,6 +104,17 @@ pub struct Passive;
 #[derive(Component)]
 pub struct Enemy;
 
+/// Blocks scattered on death, picked up by walking into them.
+#[derive(Component)]
+pub struct DroppedStack {
+    pub block_type: BlockType,
+    pub count: u32,
+}
+
+/// Seconds until a passive mob picks a new wander heading.
+#[derive(Component, Default)]
+pub struct WanderTimer(pub f32);
+
 #[derive(Component)]
 pub struct Health(pub f32);
 
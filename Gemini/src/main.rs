@@ -3,21 +3,39 @@ mod resources;
 mod systems;
 
 use bevy::prelude::*;
-use resources::{GameState, HungerDepleted, RaycastHit};
+use resources::{
+    BlockChanged, GameState, HungerDepleted, PlayerDamaged, RaycastHit, world_interaction_active,
+    world_simulation_active,
+};
+use systems::loading::{check_asset_loading, load_assets, setup_loading_screen};
 use systems::mobs::{
     mob_ai, mob_attack, mob_boundary_check, mob_damage_player, mob_death, spawn_mobs,
     update_mob_health_bars,
 };
-use systems::physics::{apply_physics, ground_check};
-use systems::player::{grab_cursor, pause_toggle, player_look, player_movement, spawn_player};
+use systems::physics::{
+    DEFAULT_PHYSICS_TICK_RATE, apply_physics, cycle_physics_tick_rate, ground_check,
+};
+use systems::player::{
+    apply_post_processing, grab_cursor, pause_toggle, player_look, player_movement, spawn_player,
+    toggle_post_processing,
+};
+use systems::save::{restore_saved_player, save_game};
+use systems::settings::{load_settings, save_settings};
 use systems::survival::{
-    craft_system, hunger_decay, respawn_system, setup_death_screen, setup_inventory_ui,
-    setup_pause_menu, setup_ui, starvation_damage, update_death_screen, update_diagnostics_ui,
-    update_inventory_ui, update_pause_menu_visibility, update_survival_ui,
+    apply_player_damage, button_interaction_system, craft_system, cycle_crosshair_style,
+    drop_inventory_on_death, give_starting_inventory, hunger_decay, pickup_dropped_stacks,
+    quit_button_system, rebuild_crosshair, respawn_system, setup_damage_vignette,
+    setup_death_screen, setup_inventory_ui, setup_pause_menu, setup_ui, starvation_damage,
+    toggle_keep_inventory, track_survival_time, update_damage_vignette, update_death_screen,
+    update_diagnostics_ui, update_inventory_ui, update_pause_menu_visibility, update_survival_ui,
 };
 use systems::world::{
-    SelectionMaterial, block_modification, block_raycast, day_night_cycle, init_assets,
-    setup_world, update_targeting,
+    SelectionMaterial, apply_block_lighting, block_modification, block_raycast, day_night_cycle,
+    hide_targeting, init_assets, setup_world, toggle_block_lighting, update_block_entities,
+    update_targeting,
+};
+use systems::world_file::{
+    ImportReport, export_world, import_world, setup_import_report, update_import_report,
 };
 
 fn main() {
@@ -28,22 +46,46 @@ fn main() {
             bevy::diagnostic::LogDiagnosticsPlugin::default(),
             MaterialPlugin::<SelectionMaterial>::default(),
         ))
+        .insert_resource(Time::<Fixed>::from_hz(DEFAULT_PHYSICS_TICK_RATE))
         .init_state::<GameState>()
         .init_resource::<resources::TimeOfDay>()
         .init_resource::<resources::Inventory>()
+        .init_resource::<resources::StartingInventory>()
+        .init_resource::<resources::GameRules>()
+        .init_resource::<resources::LifeStats>()
+        .init_resource::<resources::SpawnPoint>()
+        .init_resource::<resources::WorldGenConfig>()
+        .init_resource::<resources::MobSpawnConfig>()
+        .insert_resource(resources::WorldSeed::from_args())
+        .init_resource::<resources::CombatSettings>()
+        .init_resource::<resources::MiningState>()
+        .init_resource::<resources::GameUI>()
+        .init_resource::<resources::PostProcessSettings>()
+        .init_resource::<resources::BlockLighting>()
+        .init_resource::<resources::CrosshairSettings>()
+        .init_resource::<ImportReport>()
         .add_event::<RaycastHit>()
+        .add_event::<BlockChanged>()
         .add_event::<HungerDepleted>()
+        .add_event::<PlayerDamaged>()
         .add_systems(
             Startup,
             (
+                load_settings,
+                load_assets,
                 init_assets,
                 setup_world,
                 spawn_player,
+                give_starting_inventory,
+                restore_saved_player,
                 setup_ui,
+                setup_import_report,
+                setup_damage_vignette,
                 setup_pause_menu,
                 setup_death_screen,
                 spawn_mobs,
                 setup_inventory_ui,
+                setup_loading_screen,
             )
                 .chain(),
         )
@@ -54,37 +96,70 @@ fn main() {
                 update_pause_menu_visibility,
                 grab_cursor,
                 update_death_screen,
+                button_interaction_system,
                 respawn_system.run_if(in_state(GameState::GameOver)),
+                quit_button_system.run_if(in_state(GameState::GameOver)),
+                toggle_post_processing,
+                apply_post_processing.after(toggle_post_processing),
+                toggle_block_lighting,
+                apply_block_lighting.after(toggle_block_lighting),
+                check_asset_loading.run_if(in_state(GameState::Loading)),
+                cycle_physics_tick_rate,
+                toggle_keep_inventory,
+                (cycle_crosshair_style, rebuild_crosshair).chain(),
+                (export_world, import_world, save_game).run_if(in_state(GameState::InGame)),
+                update_import_report,
+                save_settings,
             ),
         )
+        .add_systems(OnEnter(GameState::GameOver), drop_inventory_on_death)
         .add_systems(
             Update,
             (
                 player_look,
                 player_movement,
-                block_raycast,
-                block_modification,
-                update_targeting,
+                block_raycast.run_if(world_interaction_active),
+                block_modification.run_if(world_interaction_active),
+                update_block_entities,
+                update_targeting.run_if(world_interaction_active),
+                hide_targeting.run_if(not(world_interaction_active)),
                 update_survival_ui,
-                day_night_cycle,
-                mob_ai,
-                mob_boundary_check,
                 mob_attack,
                 mob_damage_player,
+                apply_player_damage,
+                update_damage_vignette,
                 mob_death,
                 update_mob_health_bars,
                 update_inventory_ui,
-                update_diagnostics_ui,
                 craft_system,
+                pickup_dropped_stacks,
+                track_survival_time,
             )
                 .run_if(in_state(GameState::InGame))
                 .chain(),
         )
+        .add_systems(
+            Update,
+            (day_night_cycle, mob_ai, mob_boundary_check)
+                .run_if(world_simulation_active)
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            update_diagnostics_ui.run_if(world_simulation_active),
+        )
         .add_systems(
             FixedUpdate,
-            (hunger_decay, starvation_damage, apply_physics, ground_check)
+            (hunger_decay, starvation_damage)
                 .run_if(in_state(GameState::InGame))
                 .chain(),
         )
+        .add_systems(
+            FixedUpdate,
+            (apply_physics, ground_check)
+                .after(starvation_damage)
+                .run_if(world_simulation_active)
+                .chain(),
+        )
         .run();
 }
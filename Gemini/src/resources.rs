@@ -1,5 +1,7 @@
 use crate::components::BlockType;
 use bevy::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use std::collections::HashMap;
 
 #[derive(Resource, Default)]
@@ -17,20 +19,198 @@ pub struct MaterialHandles {
     pub leaves: Handle<StandardMaterial>,
 }
 
+impl MaterialHandles {
+    pub fn get(&self, block_type: BlockType) -> Handle<StandardMaterial> {
+        match block_type {
+            BlockType::Grass => self.grass.clone(),
+            BlockType::Dirt => self.dirt.clone(),
+            BlockType::Stone => self.stone.clone(),
+            BlockType::Wood => self.wood.clone(),
+            BlockType::Leaves => self.leaves.clone(),
+        }
+    }
+
+    pub fn all(&self) -> [&Handle<StandardMaterial>; 5] {
+        [
+            &self.grass,
+            &self.dirt,
+            &self.stone,
+            &self.wood,
+            &self.leaves,
+        ]
+    }
+}
+
+/// Lit blocks darken at night and take directional shading from the sun;
+/// `unlit` keeps the original flat colors.
+#[derive(Resource, Default)]
+pub struct BlockLighting {
+    pub unlit: bool,
+}
+
+/// Normalized time of day: 0.0 is sunrise, 0.25 noon, 0.5 sunset and 0.75
+/// midnight.
+#[derive(Resource)]
+pub struct TimeOfDay {
+    pub time: f32,
+    pub day_length_seconds: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            day_length_seconds: 60.0,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// Sine of the sun's angle: 1.0 at noon, -1.0 at midnight.
+    pub fn sun_height(&self) -> f32 {
+        (self.time * std::f32::consts::TAU).sin()
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.sun_height() < -0.1
+    }
+
+    /// Sunlight strength from 0.05 (night) to 1.0 (noon).
+    pub fn sun_intensity(&self) -> f32 {
+        self.sun_height().max(0.05)
+    }
+
+    pub fn sky_color(&self) -> Color {
+        let sun_y = self.sun_height();
+        if sun_y < -0.1 {
+            Color::srgb(0.02, 0.02, 0.05) // Dark Night
+        } else if sun_y < 0.2 {
+            Color::srgb(0.8, 0.4, 0.2) // Sunset/Sunrise
+        } else {
+            Color::srgb(0.5, 0.7, 1.0) // Day
+        }
+    }
+
+    pub fn ambient_color(&self) -> Color {
+        if self.is_night() {
+            Color::srgb(0.1, 0.1, 0.3)
+        } else {
+            Color::srgb(0.6, 0.7, 1.0)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CrosshairStyle {
+    #[default]
+    Dot,
+    Cross,
+    Circle,
+}
+
+impl CrosshairStyle {
+    pub fn next(self) -> Self {
+        match self {
+            CrosshairStyle::Dot => CrosshairStyle::Cross,
+            CrosshairStyle::Cross => CrosshairStyle::Circle,
+            CrosshairStyle::Circle => CrosshairStyle::Dot,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CrosshairStyle::Dot => "dot",
+            CrosshairStyle::Cross => "cross",
+            CrosshairStyle::Circle => "circle",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            CrosshairStyle::Dot,
+            CrosshairStyle::Cross,
+            CrosshairStyle::Circle,
+        ]
+        .into_iter()
+        .find(|style| style.name() == name)
+    }
+}
+
+/// Crosshair look. `size` is the cross/circle extent, `thickness` the line
+/// width (the dot is twice as wide).
+#[derive(Resource)]
+pub struct CrosshairSettings {
+    pub style: CrosshairStyle,
+    pub size: f32,
+    pub thickness: f32,
+    pub color: Color,
+}
+
+impl Default for CrosshairSettings {
+    fn default() -> Self {
+        Self {
+            style: CrosshairStyle::Dot,
+            size: 16.0,
+            thickness: 2.0,
+            color: Color::srgba(0.0, 1.0, 1.0, 0.8),
+        }
+    }
+}
+
+/// Camera post-processing. Off by default: bloom and HDR cost GPU time and
+/// make the unlit blocks glow.
 #[derive(Resource, Default)]
-pub struct TimeOfDay(pub f32); // 0.0 to 1.0 (normalized day)
+pub struct PostProcessSettings {
+    pub bloom: bool, // also switches the camera to HDR with Reinhard tonemapping
+}
 
 #[derive(Resource, Default)]
 pub struct Inventory {
     pub items: HashMap<crate::components::BlockType, u32>,
 }
 
+/// What the inventory holds when the game starts. Off by default for a
+/// survival start with nothing; turn `starter_kit` on to begin stocked.
+#[derive(Resource)]
+pub struct StartingInventory {
+    pub starter_kit: bool,
+    pub kit: Vec<(BlockType, u32)>,
+}
+
+impl Default for StartingInventory {
+    fn default() -> Self {
+        Self {
+            starter_kit: false,
+            kit: vec![
+                (BlockType::Dirt, 64),
+                (BlockType::Stone, 64),
+                (BlockType::Wood, 32),
+            ],
+        }
+    }
+}
+
+/// Death penalty options. With `keep_inventory` off, dying scatters the
+/// inventory where the player fell, to be picked back up after respawning.
+#[derive(Resource, Default)]
+pub struct GameRules {
+    pub keep_inventory: bool,
+}
+
 #[derive(Resource, Default)]
 pub struct CubeMesh(pub Handle<Mesh>);
 
 #[derive(Resource)]
 pub struct SelectedBlock(pub crate::components::BlockType);
 
+/// Block being mined by holding left click, and seconds of mining put into
+/// it so far. Reset by looking away or letting go.
+#[derive(Resource, Default)]
+pub struct MiningState {
+    pub target: Option<IVec3>,
+    pub progress: f32,
+}
+
 #[derive(Event)]
 pub struct RaycastHit {
     pub coord: IVec3,
@@ -38,13 +218,276 @@ pub struct RaycastHit {
     pub entity: Option<Entity>,
 }
 
+/// Sent whenever a cell of `VoxelWorld::blocks` changes after world
+/// generation. The edit itself only touches the block map; rendering and any
+/// other reactions subscribe to this.
+#[derive(Event)]
+pub struct BlockChanged {
+    pub coord: IVec3,
+    pub old: Option<BlockType>,
+    pub new: Option<BlockType>,
+}
+
 #[derive(Event)]
 pub struct HungerDepleted;
 
+/// Damage to the player from any source. `apply_player_damage` is the only
+/// system that lowers player health, so damage feedback lives in one place.
+/// `source` is where a hit came from, if anywhere, and knocks the player
+/// away from it.
+#[derive(Event)]
+pub struct PlayerDamaged {
+    pub amount: f32,
+    pub cause: DamageCause,
+    pub source: Option<Vec3>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageCause {
+    Starvation,
+    Mob,
+}
+
+impl DamageCause {
+    pub fn description(&self) -> &'static str {
+        match self {
+            DamageCause::Starvation => "Starved to death",
+            DamageCause::Mob => "Slain by a hostile mob",
+        }
+    }
+}
+
+/// World generation knobs. `tree_density` is trees per 100 square blocks of
+/// ground; 0 leaves the world bare for building. `amplitude` is how many
+/// blocks the surface rises above its lowest point and `frequency` how
+/// quickly hills come and go.
+#[derive(Resource)]
+pub struct WorldGenConfig {
+    pub tree_density: f32,
+    pub amplitude: f32,
+    pub frequency: f32,
+}
+
+impl Default for WorldGenConfig {
+    fn default() -> Self {
+        Self {
+            tree_density: 2.5,
+            amplitude: 10.0,
+            frequency: 0.06,
+        }
+    }
+}
+
+impl WorldGenConfig {
+    /// Y of the grass block on top of the column at `x`, `z`. The same seed
+    /// always gives the same terrain.
+    pub fn surface_height(&self, seed: WorldSeed, x: i32, z: i32) -> i32 {
+        let point = Vec2::new(x as f32, z as f32) * self.frequency;
+        let noise = 0.7 * value_noise(seed.0, point)
+            + 0.3 * value_noise(seed.0.wrapping_add(1), point * 2.0);
+        MIN_SURFACE_HEIGHT + (noise * self.amplitude).round() as i32
+    }
+}
+
+const MIN_SURFACE_HEIGHT: i32 = 2;
+
+/// How many mobs of each kind `spawn_mobs` starts the world with.
+#[derive(Resource)]
+pub struct MobSpawnConfig {
+    pub passive_count: usize,
+    pub hostile_count: usize,
+}
+
+impl Default for MobSpawnConfig {
+    fn default() -> Self {
+        Self {
+            passive_count: 6,
+            hostile_count: 4,
+        }
+    }
+}
+
+/// Seed for everything random about a new world: terrain, trees and where
+/// mobs start. Given as `--seed <n>` on the command line, otherwise random
+/// and logged at startup so a world worth keeping can be rebuilt.
+#[derive(Resource, Clone, Copy)]
+pub struct WorldSeed(pub u64);
+
+impl WorldSeed {
+    pub const TREES: u64 = 0x7472_6565;
+    pub const MOBS: u64 = 0x6d6f_6273;
+
+    pub fn from_args() -> Self {
+        std::env::args()
+            .skip_while(|arg| arg != "--seed")
+            .nth(1)
+            .and_then(|value| value.parse().ok())
+            .map(Self)
+            .unwrap_or_else(|| {
+                let seed = Self(rand::random());
+                info!(
+                    "Random world seed {}; rerun with --seed {0} to get it back",
+                    seed.0
+                );
+                seed
+            })
+    }
+
+    /// Generator for one part of world generation. `stream` (TREES, MOBS)
+    /// keeps the parts from drawing the same sequence.
+    pub fn rng(self, stream: u64) -> StdRng {
+        StdRng::seed_from_u64(self.0 ^ stream)
+    }
+}
+
+/// Smoothly interpolated random values on a unit grid, in 0..1.
+fn value_noise(seed: u64, point: Vec2) -> f32 {
+    let cell = point.floor();
+    let t = point - cell;
+    let t = t * t * (Vec2::splat(3.0) - 2.0 * t);
+    let (x, z) = (cell.x as i64, cell.y as i64);
+    let top = lattice_value(seed, x, z).lerp(lattice_value(seed, x + 1, z), t.x);
+    let bottom = lattice_value(seed, x, z + 1).lerp(lattice_value(seed, x + 1, z + 1), t.x);
+    top.lerp(bottom, t.y)
+}
+
+fn lattice_value(seed: u64, x: i64, z: i64) -> f32 {
+    let mut hash = seed
+        ^ (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (z as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    hash = (hash ^ (hash >> 31)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash ^= hash >> 29;
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Combat tuning. `knockback_strength` is the horizontal speed of a hit's
+/// shove, for the player and mobs alike. `damage_exhaustion` is the hunger
+/// the player loses per point of damage taken.
+#[derive(Resource)]
+pub struct CombatSettings {
+    pub knockback_strength: f32,
+    pub damage_exhaustion: f32,
+}
+
+impl Default for CombatSettings {
+    fn default() -> Self {
+        Self {
+            knockback_strength: 5.0,
+            damage_exhaustion: 0.3,
+        }
+    }
+}
+
+/// Where the player comes back after dying. Starts at the initial spawn;
+/// sleeping in a bed should move it here once beds exist.
+#[derive(Resource)]
+pub struct SpawnPoint(pub Vec3);
+
+impl Default for SpawnPoint {
+    fn default() -> Self {
+        Self(Vec3::new(0.0, 5.0, 0.0))
+    }
+}
+
+impl SpawnPoint {
+    /// Raises the spawn point a block at a time until nothing overlaps a body
+    /// of the given half height, so respawning never lands inside something
+    /// built on top of it.
+    pub fn safe_position(&self, world: &VoxelWorld, half_height: f32) -> Vec3 {
+        let mut position = self.0;
+        for _ in 0..64 {
+            let column = IVec3::new(position.x.round() as i32, 0, position.z.round() as i32);
+            let feet = (position.y - half_height).round() as i32;
+            let head = (position.y + half_height).round() as i32;
+            let blocked = (feet..=head).any(|y| {
+                world
+                    .blocks
+                    .contains_key(&IVec3::new(column.x, y, column.z))
+            });
+            if !blocked {
+                break;
+            }
+            position.y += 1.0;
+        }
+        position
+    }
+}
+
+/// Every asset loaded from disk, by path. Gameplay waits in
+/// `GameState::Loading` until all `required` assets are loaded; a failed
+/// `optional` one only logs a warning.
+#[derive(Resource, Default)]
+pub struct AssetManifest {
+    pub required: Vec<(&'static str, UntypedHandle)>,
+    pub optional: Vec<(&'static str, UntypedHandle)>,
+}
+
+#[derive(Resource, Default)]
+pub struct LifeStats {
+    pub time_alive: f32,
+    pub last_damage: Option<DamageCause>,
+}
+
 #[derive(States, Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
 pub enum GameState {
+    /// Waiting on the files in `AssetManifest`; the loading screen shows
+    /// progress, or what's missing if a required file failed.
     #[default]
+    Loading,
     InGame,
     Paused,
     GameOver,
 }
+
+/// In-game overlays that take the mouse while the game keeps running.
+/// Targeting, breaking and placing blocks are suppressed while any is open
+/// so clicks meant for the menu don't reach the world.
+#[derive(Resource, Default)]
+pub struct GameUI {
+    pub inventory_open: bool,
+    pub crafting_open: bool,
+}
+
+impl GameUI {
+    pub fn any_open(&self) -> bool {
+        self.inventory_open || self.crafting_open
+    }
+}
+
+/// Run condition for systems that act on the block under the crosshair.
+pub fn world_interaction_active(game_ui: Res<GameUI>) -> bool {
+    !game_ui.any_open()
+}
+
+/// Run condition for world simulation (mobs, physics, day cycle) and the
+/// diagnostics readout, which keep going on the death screen so the player
+/// can watch the aftermath.
+pub fn world_simulation_active(state: Res<State<GameState>>) -> bool {
+    matches!(state.get(), GameState::InGame | GameState::GameOver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn the_same_seed_generates_the_same_world() {
+        let config = WorldGenConfig::default();
+        let generate = |seed: WorldSeed| {
+            let heights: Vec<i32> = (-16..16)
+                .flat_map(|x| (-16..16).map(move |z| (x, z)))
+                .map(|(x, z)| config.surface_height(seed, x, z))
+                .collect();
+            let trees: Vec<u32> = {
+                let mut rng = seed.rng(WorldSeed::TREES);
+                (0..8).map(|_| rng.random()).collect()
+            };
+            (heights, trees)
+        };
+
+        assert_eq!(generate(WorldSeed(99)), generate(WorldSeed(99)));
+        assert_ne!(generate(WorldSeed(99)).0, generate(WorldSeed(100)).0);
+        assert_ne!(generate(WorldSeed(99)).1, generate(WorldSeed(100)).1);
+    }
+}
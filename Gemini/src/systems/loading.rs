@@ -0,0 +1,142 @@
+use crate::resources::{AssetManifest, GameState};
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+
+/// Required by `SelectionMaterial`; loaded here so a missing file stops on
+/// the loading screen instead of leaving the selection box invisible.
+pub const SELECTION_SHADER_PATH: &str = "shaders/selection.wgsl";
+
+#[derive(Component)]
+pub struct LoadingScreen;
+
+#[derive(Component)]
+pub struct LoadingBar;
+
+#[derive(Component)]
+pub struct LoadingText;
+
+pub fn load_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AssetManifest {
+        required: vec![(
+            SELECTION_SHADER_PATH,
+            asset_server.load::<Shader>(SELECTION_SHADER_PATH).untyped(),
+        )],
+        optional: Vec::new(),
+    });
+}
+
+pub fn setup_loading_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            LoadingScreen,
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.05, 0.05, 0.08)),
+            GlobalZIndex(10),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Loading..."),
+                TextFont {
+                    font_size: 24.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                TextLayout::new_with_justify(JustifyText::Center),
+                LoadingText,
+            ));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(300.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.1)),
+                    BorderRadius::all(Val::Px(6.0)),
+                ))
+                .with_children(|track| {
+                    track.spawn((
+                        Node {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.2, 0.7, 0.4)),
+                        BorderRadius::all(Val::Px(6.0)),
+                        LoadingBar,
+                    ));
+                });
+        });
+}
+
+/// Tracks the manifest's load states: fills the progress bar, starts the
+/// game once every required asset is in, and lists the missing files if any
+/// required one failed.
+pub fn check_asset_loading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut manifest: ResMut<AssetManifest>,
+    mut next_state: ResMut<NextState<GameState>>,
+    screen_query: Query<Entity, With<LoadingScreen>>,
+    mut bar_query: Query<&mut Node, With<LoadingBar>>,
+    mut text_query: Query<&mut Text, With<LoadingText>>,
+) {
+    // Optional assets never hold up loading; drop any that failed
+    manifest.optional.retain(|(path, handle)| {
+        let failed = matches!(
+            asset_server.get_load_state(handle.id()),
+            Some(LoadState::Failed(_))
+        );
+        if failed {
+            warn!("Optional asset {path} failed to load; continuing without it");
+        }
+        !failed
+    });
+
+    let mut loaded = 0;
+    let mut missing = Vec::new();
+    for (path, handle) in &manifest.required {
+        match asset_server.get_load_state(handle.id()) {
+            Some(LoadState::Loaded) => loaded += 1,
+            Some(LoadState::Failed(err)) => {
+                error!("Required asset {path} failed to load: {err}");
+                missing.push(*path);
+            }
+            _ => {}
+        }
+    }
+
+    let total = manifest.required.len().max(1);
+    if let Ok(mut bar) = bar_query.get_single_mut() {
+        bar.width = Val::Percent(loaded as f32 / total as f32 * 100.0);
+    }
+
+    if !missing.is_empty() {
+        if let Ok(mut text) = text_query.get_single_mut() {
+            let message = format!(
+                "Missing game files:\n{}\n\nCheck that the assets folder is next to the game.",
+                missing.join("\n")
+            );
+            if text.0 != message {
+                text.0 = message;
+            }
+        }
+        return;
+    }
+
+    if loaded == manifest.required.len() {
+        for entity in screen_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        next_state.set(GameState::InGame);
+    }
+}
@@ -1,58 +1,103 @@
-use crate::components::{Enemy, Grounded, Health, Mob, Passive, Player, Velocity};
-use crate::resources::VoxelWorld;
+use crate::components::{
+    BlockType, CollisionBox, Enemy, Grounded, Health, Knockback, Mob, Passive, Player, Velocity,
+    WanderTimer,
+};
+use crate::resources::{
+    CombatSettings, DamageCause, MobSpawnConfig, PlayerDamaged, VoxelWorld, WorldSeed,
+};
+use crate::systems::physics::apply_knockback;
 use bevy::prelude::*;
 use rand::Rng;
 
+const WANDER_INTERVAL: std::ops::Range<f32> = 1.0..3.0;
+const MOB_SPAWN_HEIGHT: i32 = 32; // above the highest hills and trees
+const MOB_SPAWN_CLEARANCE: f32 = 4.0; // keeps starting mobs off the player
+const MOB_SPAWN_ATTEMPTS: usize = 20;
+
 pub fn spawn_mobs(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    seed: Res<WorldSeed>,
+    world: Res<VoxelWorld>,
+    config: Res<MobSpawnConfig>,
 ) {
-    let mesh = meshes.add(Cuboid::from_size(Vec3::splat(0.8)));
+    let mob_size = 0.8;
+    let mesh = meshes.add(Cuboid::from_size(Vec3::splat(mob_size)));
     let passive_mat = materials.add(Color::srgb(0.8, 0.8, 0.8));
     let enemy_mat = materials.add(Color::srgb(0.8, 0.2, 0.2));
 
-    let mut rng = rand::rng();
+    let mut rng = seed.rng(WorldSeed::MOBS);
 
-    // Spawn within world bounds (-16..16)
-    for _ in 0..6 {
-        let x = rng.random_range(-14.0..14.0);
-        let z = rng.random_range(-14.0..14.0);
+    for _ in 0..config.passive_count {
+        let Some(position) = valid_spawn_position(&world, &mut rng) else {
+            continue;
+        };
         commands.spawn((
             Mesh3d(mesh.clone()),
             MeshMaterial3d(passive_mat.clone()),
-            Transform::from_xyz(x, 10.0, z),
+            Transform::from_translation(position),
             Mob,
             Passive,
+            WanderTimer::default(),
             Velocity(Vec3::ZERO),
+            Knockback::default(),
             Grounded(false),
+            CollisionBox::cube(mob_size),
             Health(20.0),
         ));
     }
 
-    for _ in 0..4 {
-        let x = rng.random_range(-14.0..14.0);
-        let z = rng.random_range(-14.0..14.0);
+    for _ in 0..config.hostile_count {
+        let Some(position) = valid_spawn_position(&world, &mut rng) else {
+            continue;
+        };
         commands.spawn((
             Mesh3d(mesh.clone()),
             MeshMaterial3d(enemy_mat.clone()),
-            Transform::from_xyz(x, 10.0, z),
+            Transform::from_translation(position),
             Mob,
             Enemy,
             Velocity(Vec3::ZERO),
+            Knockback::default(),
             Grounded(false),
+            CollisionBox::cube(mob_size),
             Health(20.0),
         ));
     }
 }
 
+/// A random spot within world bounds (-16..16) for a mob to start on: on
+/// top of a grass, dirt or stone column with room above it, and away from
+/// the player's spawn. None if MOB_SPAWN_ATTEMPTS columns all fail.
+pub fn valid_spawn_position(world: &VoxelWorld, rng: &mut impl Rng) -> Option<Vec3> {
+    (0..MOB_SPAWN_ATTEMPTS).find_map(|_| {
+        let x = rng.random_range(-14..14);
+        let z = rng.random_range(-14..14);
+        if Vec2::new(x as f32, z as f32).length() < MOB_SPAWN_CLEARANCE {
+            return None;
+        }
+
+        let top = (0..=MOB_SPAWN_HEIGHT)
+            .rev()
+            .map(|y| IVec3::new(x, y, z))
+            .find(|coord| world.blocks.contains_key(coord))?;
+        let ground = matches!(
+            world.blocks[&top],
+            BlockType::Grass | BlockType::Dirt | BlockType::Stone
+        );
+        // Block centres sit on whole coordinates; rest the mob on the top face
+        ground.then(|| top.as_vec3() + Vec3::Y)
+    })
+}
+
 pub fn mob_ai(
     time: Res<Time>,
     player_query: Query<&Transform, With<Player>>,
     mut mob_query: Query<
         (
             &mut Transform,
-            Option<&Passive>,
+            Option<&mut WanderTimer>,
             Option<&Enemy>,
             &mut Velocity,
         ),
@@ -67,10 +112,13 @@ pub fn mob_ai(
 
     let mut rng = rand::rng();
 
-    for (mut transform, passive, enemy, mut velocity) in mob_query.iter_mut() {
-        if passive.is_some() {
-            // Wander
-            if rng.random_bool(0.01) {
+    for (mut transform, wander_timer, enemy, mut velocity) in mob_query.iter_mut() {
+        if let Some(mut wander_timer) = wander_timer {
+            // Wander, turning every WANDER_INTERVAL seconds or so whatever
+            // the frame rate
+            wander_timer.0 -= delta;
+            if wander_timer.0 <= 0.0 {
+                wander_timer.0 = rng.random_range(WANDER_INTERVAL);
                 let angle = rng.random_range(0.0..std::f32::consts::TAU);
                 velocity.0.x = angle.cos() * 2.0;
                 velocity.0.z = angle.sin() * 2.0;
@@ -92,19 +140,21 @@ pub fn mob_ai(
 pub fn mob_damage_player(
     time: Res<Time>,
     player_query: Query<&Transform, With<Player>>,
-    mut player_health_query: Query<&mut Health, With<Player>>,
     mob_query: Query<&Transform, (With<Mob>, With<Enemy>)>,
+    mut damage_events: EventWriter<PlayerDamaged>,
 ) {
     let Ok(player_transform) = player_query.get_single() else {
         return;
     };
     let player_pos = player_transform.translation;
 
-    if let Ok(mut health) = player_health_query.get_single_mut() {
-        for mob_transform in mob_query.iter() {
-            if mob_transform.translation.distance(player_pos) < 1.0 {
-                health.0 -= 3.0 * time.delta_secs();
-            }
+    for mob_transform in mob_query.iter() {
+        if mob_transform.translation.distance(player_pos) < 1.0 {
+            damage_events.send(PlayerDamaged {
+                amount: 3.0 * time.delta_secs(),
+                cause: DamageCause::Mob,
+                source: Some(mob_transform.translation),
+            });
         }
     }
 }
@@ -119,9 +169,10 @@ pub fn mob_death(mut commands: Commands, query: Query<(Entity, &Health), With<Mo
 
 pub fn mob_attack(
     mouse_input: Res<ButtonInput<MouseButton>>,
+    combat: Res<CombatSettings>,
     player_query: Query<&Transform, With<Player>>,
     mut mob_health_query: Query<
-        (&Transform, &mut Health, &mut Velocity),
+        (&Transform, &mut Health, &mut Velocity, &mut Knockback),
         (With<Mob>, Without<Player>),
     >,
 ) {
@@ -130,7 +181,9 @@ pub fn mob_attack(
             let player_pos = player_transform.translation;
             let forward = player_transform.forward();
 
-            for (mob_transform, mut health, mut velocity) in mob_health_query.iter_mut() {
+            for (mob_transform, mut health, mut velocity, mut knockback) in
+                mob_health_query.iter_mut()
+            {
                 let to_mob = mob_transform.translation - player_pos;
                 let dist = to_mob.length();
                 let dot = forward.dot(to_mob.normalize_or_zero());
@@ -139,11 +192,13 @@ pub fn mob_attack(
                 if dist < 3.5 && dot > 0.5 {
                     health.0 -= 10.0;
 
-                    // Apply knockback
-                    let knockback_force = 5.0;
-                    let mut knockback_dir = to_mob.normalize_or_zero();
-                    knockback_dir.y = 0.5; // Slight upward pop
-                    velocity.0 += knockback_dir * knockback_force;
+                    apply_knockback(
+                        &mut velocity,
+                        &mut knockback,
+                        player_pos,
+                        mob_transform.translation,
+                        combat.knockback_strength,
+                    );
                 }
             }
         }
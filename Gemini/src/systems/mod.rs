@@ -1,5 +1,9 @@
+pub mod loading;
 pub mod mobs;
 pub mod physics;
 pub mod player;
+pub mod save;
+pub mod settings;
 pub mod survival;
 pub mod world;
+pub mod world_file;
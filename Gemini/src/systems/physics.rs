@@ -1,19 +1,82 @@
-use crate::components::{Grounded, Velocity};
+use crate::components::{CollisionBox, Grounded, Knockback, Velocity};
 use crate::resources::VoxelWorld;
 use bevy::prelude::*;
 
+/// FixedUpdate rates F6 cycles through: Minecraft's 20 Hz, Bevy's default
+/// 64 Hz and 128 Hz. Rendering isn't interpolated between ticks, so 20 Hz
+/// movement visibly steps, and collision only tests where a body ends up,
+/// so anything moving over about a block per tick can skip through a thin
+/// floor.
+pub const PHYSICS_TICK_RATES: [f64; 3] = [20.0, 64.0, 128.0];
+pub const DEFAULT_PHYSICS_TICK_RATE: f64 = 64.0;
+
+/// Upward speed of a knockback, as a fraction of its horizontal strength.
+const KNOCKBACK_LIFT: f32 = 0.6;
+/// How quickly knockback dies away, per second.
+const KNOCKBACK_DRAG: f32 = 8.0;
+
+/// Shoves the body at `to` away from `from`. The hop goes straight into
+/// `Velocity` so gravity brings it down; the horizontal part replaces any
+/// knockback still in progress rather than stacking on it.
+pub fn apply_knockback(
+    velocity: &mut Velocity,
+    knockback: &mut Knockback,
+    from: Vec3,
+    to: Vec3,
+    strength: f32,
+) {
+    let direction = (to - from).with_y(0.0).normalize_or_zero();
+    knockback.0 = direction * strength;
+    velocity.y = velocity.y.max(strength * KNOCKBACK_LIFT);
+}
+
+pub fn cycle_physics_tick_rate(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F6) {
+        return;
+    }
+    let current = fixed_time.timestep().as_secs_f64().recip();
+    let next = PHYSICS_TICK_RATES
+        .iter()
+        .copied()
+        .find(|&rate| rate > current + 0.5)
+        .unwrap_or(PHYSICS_TICK_RATES[0]);
+    fixed_time.set_timestep_hz(next);
+    info!("Physics tick rate: {next} Hz");
+}
+
+/// Everything `apply_physics` moves; bodies without a collision box use the
+/// player's.
+type PhysicsBody = (
+    &'static mut Transform,
+    &'static mut Velocity,
+    &'static mut Grounded,
+    Option<&'static CollisionBox>,
+    Option<&'static mut Knockback>,
+);
+
 pub fn apply_physics(
-    mut query: Query<(&mut Transform, &mut Velocity, &mut Grounded)>,
+    mut query: Query<PhysicsBody>,
     world: Res<VoxelWorld>,
     time: Res<Time<Fixed>>,
 ) {
     let delta = time.delta_secs();
     let gravity = -9.81;
-    let player_radius = 0.3;
-    let player_height = 1.8;
-    let half_height = player_height / 2.0;
 
-    for (mut transform, mut velocity, mut grounded) in query.iter_mut() {
+    for (mut transform, mut velocity, mut grounded, collision_box, mut knockback) in
+        query.iter_mut()
+    {
+        let collision_box = collision_box.copied().unwrap_or_default();
+        let shove = knockback
+            .as_deref()
+            .map_or(Vec3::ZERO, |knockback| knockback.0);
+        let move_x = velocity.x + shove.x;
+        let move_z = velocity.z + shove.z;
+        let radius = collision_box.half_width;
+        let half_height = collision_box.half_height;
+
         // Apply gravity
         velocity.y += gravity * delta;
 
@@ -31,10 +94,10 @@ pub fn apply_physics(
 
         // Check corners and center
         let horizontal_offsets = [
-            Vec3::new(player_radius, 0.0, player_radius),
-            Vec3::new(player_radius, 0.0, -player_radius),
-            Vec3::new(-player_radius, 0.0, player_radius),
-            Vec3::new(-player_radius, 0.0, -player_radius),
+            Vec3::new(radius, 0.0, radius),
+            Vec3::new(radius, 0.0, -radius),
+            Vec3::new(-radius, 0.0, radius),
+            Vec3::new(-radius, 0.0, -radius),
             Vec3::ZERO,
         ];
 
@@ -63,12 +126,12 @@ pub fn apply_physics(
         pos.y = next_y;
 
         // Try X movement
-        let mut next_x = pos.x + velocity.x * delta;
+        let mut next_x = pos.x + move_x * delta;
         let mut hit_x = false;
-        let check_x = if velocity.x < 0.0 {
-            next_x - player_radius
+        let check_x = if move_x < 0.0 {
+            next_x - radius
         } else {
-            next_x + player_radius
+            next_x + radius
         };
 
         // Check multiple heights (feet, waist, head)
@@ -84,22 +147,25 @@ pub fn apply_physics(
         if hit_x {
             let block_x = check_x.round() as f32;
             next_x = block_x
-                + (if velocity.x < 0.0 {
-                    0.5 + player_radius
+                + (if move_x < 0.0 {
+                    0.5 + radius
                 } else {
-                    -0.5 - player_radius
+                    -0.5 - radius
                 });
             velocity.x = 0.0;
+            if let Some(knockback) = knockback.as_deref_mut() {
+                knockback.x = 0.0;
+            }
         }
         pos.x = next_x;
 
         // Try Z movement
-        let mut next_z = pos.z + velocity.z * delta;
+        let mut next_z = pos.z + move_z * delta;
         let mut hit_z = false;
-        let check_z = if velocity.z < 0.0 {
-            next_z - player_radius
+        let check_z = if move_z < 0.0 {
+            next_z - radius
         } else {
-            next_z + player_radius
+            next_z + radius
         };
 
         for h_off in [-half_height + 0.1, 0.0, half_height - 0.1] {
@@ -114,15 +180,25 @@ pub fn apply_physics(
         if hit_z {
             let block_z = check_z.round() as f32;
             next_z = block_z
-                + (if velocity.z < 0.0 {
-                    0.5 + player_radius
+                + (if move_z < 0.0 {
+                    0.5 + radius
                 } else {
-                    -0.5 - player_radius
+                    -0.5 - radius
                 });
             velocity.z = 0.0;
+            if let Some(knockback) = knockback.as_deref_mut() {
+                knockback.z = 0.0;
+            }
         }
         pos.z = next_z;
 
+        if let Some(knockback) = knockback.as_deref_mut() {
+            knockback.0 *= (-KNOCKBACK_DRAG * delta).exp();
+            if knockback.0.length_squared() < 0.01 {
+                knockback.0 = Vec3::ZERO;
+            }
+        }
+
         transform.translation = pos;
     }
 }
@@ -130,3 +206,63 @@ pub fn apply_physics(
 pub fn ground_check() {
     // Merged into apply_physics
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::BlockType;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    /// Drops a body with `collision_box` onto a flat stone floor whose top
+    /// face is at y = 0.5 and returns where it settles.
+    fn settle_on_floor(collision_box: CollisionBox) -> (f32, bool) {
+        let mut world = VoxelWorld::default();
+        for x in -2..=2 {
+            for z in -2..=2 {
+                world.blocks.insert(IVec3::new(x, 0, z), BlockType::Stone);
+            }
+        }
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(world)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1.0 / DEFAULT_PHYSICS_TICK_RATE,
+            )))
+            .add_systems(FixedUpdate, apply_physics);
+        let body = app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(0.0, 3.0, 0.0),
+                Velocity::default(),
+                Grounded::default(),
+                collision_box,
+            ))
+            .id();
+
+        for _ in 0..200 {
+            app.update();
+        }
+        let world = app.world();
+        (
+            world.get::<Transform>(body).unwrap().translation.y,
+            world.get::<Grounded>(body).unwrap().0,
+        )
+    }
+
+    #[test]
+    fn mob_cube_rests_on_the_ground() {
+        let (y, grounded) = settle_on_floor(CollisionBox::cube(0.8));
+        assert!(grounded);
+        // Bottom face flush with the floor's top face, not half a block up.
+        assert!((y - 0.4 - 0.5).abs() < 0.01, "mob settled at y = {y}");
+    }
+
+    #[test]
+    fn player_rests_on_the_ground() {
+        let (y, grounded) = settle_on_floor(CollisionBox::PLAYER);
+        assert!(grounded);
+        assert!((y - 0.9 - 0.5).abs() < 0.01, "player settled at y = {y}");
+    }
+}
@@ -1,21 +1,27 @@
-use crate::components::{Grounded, Health, Hunger, MainCamera, Player, Stamina, Velocity};
-use crate::resources::GameState;
+use crate::components::{
+    CollisionBox, Grounded, Health, Hunger, IFrames, Knockback, MainCamera, Player, Stamina,
+    Velocity,
+};
+use crate::resources::{GameState, PostProcessSettings, SpawnPoint};
 use bevy::core_pipeline::bloom::Bloom;
 use bevy::core_pipeline::tonemapping::Tonemapping;
 use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, PrimaryWindow};
 
-pub fn spawn_player(mut commands: Commands) {
+pub fn spawn_player(mut commands: Commands, spawn_point: Res<SpawnPoint>) {
     commands
         .spawn((
             Player,
             Velocity::default(),
+            Knockback::default(),
+            IFrames::default(),
             Grounded(false),
+            CollisionBox::PLAYER,
             Health(100.0),
             Hunger(100.0),
             Stamina(100.0),
-            Transform::from_xyz(0.0, 5.0, 0.0),
+            Transform::from_translation(spawn_point.0),
             Visibility::default(),
             InheritedVisibility::default(),
         ))
@@ -26,11 +32,8 @@ pub fn spawn_player(mut commands: Commands) {
                 Transform::from_xyz(0.0, 0.6, 0.0),
                 Camera {
                     clear_color: ClearColorConfig::Custom(Color::srgb(0.1, 0.1, 0.15)),
-                    hdr: true,
                     ..default()
                 },
-                Tonemapping::ReinhardLuminance,
-                Bloom::default(),
                 DistanceFog {
                     color: Color::srgb(0.1, 0.1, 0.15),
                     falloff: FogFalloff::Linear {
@@ -134,7 +137,7 @@ pub fn grab_cursor(
                     window.cursor_options.visible = false;
                 }
             }
-            GameState::Paused | GameState::GameOver => {
+            GameState::Loading | GameState::Paused | GameState::GameOver => {
                 if window.cursor_options.grab_mode != CursorGrabMode::None {
                     window.cursor_options.grab_mode = CursorGrabMode::None;
                     window.cursor_options.visible = true;
@@ -154,7 +157,7 @@ pub fn pause_toggle(
         match state.get() {
             GameState::InGame => next_state.set(GameState::Paused),
             GameState::Paused => next_state.set(GameState::InGame),
-            GameState::GameOver => {}
+            GameState::Loading | GameState::GameOver => {}
         }
     }
 
@@ -162,3 +165,35 @@ pub fn pause_toggle(
         exit.send(AppExit::Success);
     }
 }
+
+pub fn toggle_post_processing(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<PostProcessSettings>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        settings.bloom = !settings.bloom;
+    }
+}
+
+/// Adds or removes bloom and HDR on the player camera to match the settings.
+pub fn apply_post_processing(
+    mut commands: Commands,
+    settings: Res<PostProcessSettings>,
+    mut camera_query: Query<(Entity, &mut Camera, &mut Tonemapping), With<MainCamera>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let Ok((entity, mut camera, mut tonemapping)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    camera.hdr = settings.bloom;
+    if settings.bloom {
+        *tonemapping = Tonemapping::ReinhardLuminance;
+        commands.entity(entity).insert(Bloom::default());
+    } else {
+        *tonemapping = Tonemapping::None;
+        commands.entity(entity).remove::<Bloom>();
+    }
+}
@@ -0,0 +1,191 @@
+//! F5 saves the world and player to `SAVE_PATH`. `setup_world` loads the
+//! save at startup in place of generating terrain, and
+//! `restore_saved_player` puts the player back once they're spawned.
+
+use crate::components::{BlockType, Health, Hunger, Player};
+use crate::resources::{Inventory, TimeOfDay, VoxelWorld};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+pub const SAVE_PATH: &str = "save.ron";
+pub const SAVE_VERSION: u32 = 1;
+
+/// The world and player as saved. A save that fails to parse or has
+/// another `SAVE_VERSION` is ignored and a new world generated instead.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub blocks: Vec<((i32, i32, i32), BlockType)>,
+    pub player_position: (f32, f32, f32),
+    pub health: f32,
+    pub hunger: f32,
+    pub inventory: Vec<(BlockType, u32)>,
+    pub time_of_day: f32,
+}
+
+impl SaveGame {
+    /// The save at `SAVE_PATH`, if there is a usable one.
+    pub fn load() -> Option<Self> {
+        let contents = fs::read_to_string(SAVE_PATH).ok()?;
+        Self::parse(&contents)
+            .inspect_err(|err| warn!("Ignoring save: {err}; generating a new world"))
+            .ok()
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let save = ron::from_str::<Self>(contents).map_err(|err| err.to_string())?;
+        if save.version != SAVE_VERSION {
+            return Err(format!(
+                "save version {}, expected {SAVE_VERSION}",
+                save.version
+            ));
+        }
+        Ok(save)
+    }
+
+    pub fn serialize(&self) -> Result<String, String> {
+        ron::to_string(self).map_err(|err| err.to_string())
+    }
+
+    pub fn world_blocks(&self) -> HashMap<IVec3, BlockType> {
+        self.blocks
+            .iter()
+            .map(|&((x, y, z), block_type)| (IVec3::new(x, y, z), block_type))
+            .collect()
+    }
+}
+
+/// Save read by `setup_world`, waiting for the player to be spawned so
+/// `restore_saved_player` can apply the rest of it.
+#[derive(Resource)]
+pub struct LoadedSave(pub SaveGame);
+
+/// Puts the player, their inventory and the time of day back as saved.
+pub fn restore_saved_player(
+    mut commands: Commands,
+    save: Option<Res<LoadedSave>>,
+    mut player_query: Query<(&mut Transform, &mut Health, &mut Hunger), With<Player>>,
+    mut inventory: ResMut<Inventory>,
+    mut time_of_day: ResMut<TimeOfDay>,
+) {
+    let Some(save) = save else {
+        return;
+    };
+    let save = &save.0;
+    if let Ok((mut transform, mut health, mut hunger)) = player_query.get_single_mut() {
+        let (x, y, z) = save.player_position;
+        transform.translation = Vec3::new(x, y, z);
+        health.0 = save.health;
+        hunger.0 = save.hunger;
+    }
+    inventory.items = save.inventory.iter().copied().collect();
+    time_of_day.time = save.time_of_day;
+    commands.remove_resource::<LoadedSave>();
+}
+
+/// F5 writes the world and player to `SAVE_PATH`.
+pub fn save_game(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    world: Res<VoxelWorld>,
+    player_query: Query<(&Transform, &Health, &Hunger), With<Player>>,
+    inventory: Res<Inventory>,
+    time_of_day: Res<TimeOfDay>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F5) {
+        return;
+    }
+    let Ok((transform, health, hunger)) = player_query.get_single() else {
+        return;
+    };
+    let position = transform.translation;
+    let save = SaveGame {
+        version: SAVE_VERSION,
+        blocks: world
+            .blocks
+            .iter()
+            .map(|(coord, &block_type)| ((coord.x, coord.y, coord.z), block_type))
+            .collect(),
+        player_position: (position.x, position.y, position.z),
+        health: health.0,
+        hunger: hunger.0,
+        inventory: inventory
+            .items
+            .iter()
+            .map(|(&block_type, &count)| (block_type, count))
+            .collect(),
+        time_of_day: time_of_day.time,
+    };
+
+    match save
+        .serialize()
+        .and_then(|contents| fs::write(SAVE_PATH, contents).map_err(|err| err.to_string()))
+    {
+        Ok(()) => info!("Saved {} blocks to {SAVE_PATH}", save.blocks.len()),
+        Err(err) => warn!("Failed to save world: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> SaveGame {
+        SaveGame {
+            version: SAVE_VERSION,
+            blocks: vec![((0, 0, 0), BlockType::Grass), ((1, -2, 3), BlockType::Wood)],
+            player_position: (0.5, 4.0, -2.0),
+            health: 62.5,
+            hunger: 40.0,
+            inventory: vec![(BlockType::Dirt, 12)],
+            time_of_day: 0.6,
+        }
+    }
+
+    #[test]
+    fn save_round_trips_through_ron() {
+        let save = sample();
+        let parsed = SaveGame::parse(&save.serialize().unwrap()).unwrap();
+        assert_eq!(parsed, save);
+        assert_eq!(
+            parsed.world_blocks()[&IVec3::new(1, -2, 3)],
+            BlockType::Wood
+        );
+    }
+
+    #[test]
+    fn corrupt_or_old_saves_are_refused() {
+        assert!(SaveGame::parse("(version: 1, blocks: [").is_err());
+        let old = SaveGame {
+            version: SAVE_VERSION + 1,
+            ..sample()
+        };
+        assert!(SaveGame::parse(&old.serialize().unwrap()).is_err());
+    }
+
+    #[test]
+    fn loaded_save_restores_the_player() {
+        let mut app = App::new();
+        app.init_resource::<Inventory>()
+            .init_resource::<TimeOfDay>()
+            .insert_resource(LoadedSave(sample()))
+            .add_systems(Update, restore_saved_player);
+        let player = app
+            .world_mut()
+            .spawn((Player, Transform::default(), Health(100.0), Hunger(100.0)))
+            .id();
+        app.update();
+
+        let world = app.world();
+        assert_eq!(
+            world.get::<Transform>(player).unwrap().translation,
+            Vec3::new(0.5, 4.0, -2.0)
+        );
+        assert_eq!(world.get::<Health>(player).unwrap().0, 62.5);
+        assert_eq!(world.get::<Hunger>(player).unwrap().0, 40.0);
+        assert_eq!(world.resource::<Inventory>().items[&BlockType::Dirt], 12);
+        assert_eq!(world.resource::<TimeOfDay>().time, 0.6);
+        assert!(!world.contains_resource::<LoadedSave>());
+    }
+}
@@ -0,0 +1,252 @@
+use crate::resources::{
+    BlockLighting, CombatSettings, CrosshairSettings, CrosshairStyle, GameRules,
+    PostProcessSettings, StartingInventory, WorldGenConfig,
+};
+use crate::systems::physics::DEFAULT_PHYSICS_TICK_RATE;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::fs;
+
+pub const SETTINGS_PATH: &str = "settings.cfg";
+
+/// Every persisted setting, written to `SETTINGS_PATH` as one `key value`
+/// line each. The live values stay in their own resources; this is only
+/// what goes to and from disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub bloom: bool,
+    pub unlit_blocks: bool,
+    pub keep_inventory: bool,
+    pub starter_kit: bool,
+    pub tree_density: f32,
+    pub knockback_strength: f32,
+    pub damage_exhaustion: f32,
+    pub physics_tick_rate: f64,
+    pub crosshair_style: CrosshairStyle,
+    pub crosshair_size: f32,
+    pub crosshair_thickness: f32,
+    pub crosshair_color: Color,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            bloom: PostProcessSettings::default().bloom,
+            unlit_blocks: BlockLighting::default().unlit,
+            keep_inventory: GameRules::default().keep_inventory,
+            starter_kit: StartingInventory::default().starter_kit,
+            tree_density: WorldGenConfig::default().tree_density,
+            knockback_strength: CombatSettings::default().knockback_strength,
+            damage_exhaustion: CombatSettings::default().damage_exhaustion,
+            physics_tick_rate: DEFAULT_PHYSICS_TICK_RATE,
+            crosshair_style: CrosshairSettings::default().style,
+            crosshair_size: CrosshairSettings::default().size,
+            crosshair_thickness: CrosshairSettings::default().thickness,
+            crosshair_color: CrosshairSettings::default().color,
+        }
+    }
+}
+
+impl Settings {
+    /// Defaults for a missing file, and for any line that doesn't parse.
+    pub fn load() -> Self {
+        fs::read_to_string(SETTINGS_PATH)
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or_default()
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut settings = Self::default();
+        for line in contents.lines() {
+            let Some((key, value)) = line.trim().split_once(' ') else {
+                continue;
+            };
+            let on = match value {
+                "on" => Some(true),
+                "off" => Some(false),
+                _ => None,
+            };
+            let parsed = match key {
+                "bloom" => on.map(|on| settings.bloom = on),
+                "unlit_blocks" => on.map(|on| settings.unlit_blocks = on),
+                "keep_inventory" => on.map(|on| settings.keep_inventory = on),
+                "starter_kit" => on.map(|on| settings.starter_kit = on),
+                "tree_density" => value.parse().ok().map(|v| settings.tree_density = v),
+                "knockback_strength" => value.parse().ok().map(|v| settings.knockback_strength = v),
+                "damage_exhaustion" => value
+                    .parse()
+                    .ok()
+                    .filter(|&v: &f32| v >= 0.0)
+                    .map(|v| settings.damage_exhaustion = v),
+                "physics_tick_rate" => value
+                    .parse()
+                    .ok()
+                    .filter(|&v: &f64| v > 0.0)
+                    .map(|v| settings.physics_tick_rate = v),
+                "crosshair_style" => {
+                    CrosshairStyle::from_name(value).map(|v| settings.crosshair_style = v)
+                }
+                "crosshair_size" => value
+                    .parse()
+                    .ok()
+                    .filter(|&v: &f32| v > 0.0)
+                    .map(|v| settings.crosshair_size = v),
+                "crosshair_thickness" => value
+                    .parse()
+                    .ok()
+                    .filter(|&v: &f32| v > 0.0)
+                    .map(|v| settings.crosshair_thickness = v),
+                "crosshair_color" => Srgba::hex(value)
+                    .ok()
+                    .map(|v| settings.crosshair_color = v.into()),
+                _ => Some(()),
+            };
+            if parsed.is_none() {
+                warn!("Ignoring bad setting in {SETTINGS_PATH}: {line:?}");
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        fs::write(SETTINGS_PATH, self.contents())
+    }
+
+    fn contents(&self) -> String {
+        let on_off = |value: bool| if value { "on" } else { "off" };
+        format!(
+            "bloom {}\nunlit_blocks {}\nkeep_inventory {}\nstarter_kit {}\n\
+                 tree_density {}\nknockback_strength {}\ndamage_exhaustion {}\n\
+                 physics_tick_rate {}\ncrosshair_style {}\ncrosshair_size {}\n\
+                 crosshair_thickness {}\ncrosshair_color {}\n",
+            on_off(self.bloom),
+            on_off(self.unlit_blocks),
+            on_off(self.keep_inventory),
+            on_off(self.starter_kit),
+            self.tree_density,
+            self.knockback_strength,
+            self.damage_exhaustion,
+            self.physics_tick_rate,
+            self.crosshair_style.name(),
+            self.crosshair_size,
+            self.crosshair_thickness,
+            self.crosshair_color.to_srgba().to_hex(),
+        )
+    }
+}
+
+/// The resources `Settings` is read back from.
+#[derive(SystemParam)]
+pub struct CurrentSettings<'w> {
+    post_process: Res<'w, PostProcessSettings>,
+    lighting: Res<'w, BlockLighting>,
+    rules: Res<'w, GameRules>,
+    starting: Res<'w, StartingInventory>,
+    world_gen: Res<'w, WorldGenConfig>,
+    combat: Res<'w, CombatSettings>,
+    fixed_time: Res<'w, Time<Fixed>>,
+    crosshair: Res<'w, CrosshairSettings>,
+}
+
+impl CurrentSettings<'_> {
+    fn snapshot(&self) -> Settings {
+        Settings {
+            bloom: self.post_process.bloom,
+            unlit_blocks: self.lighting.unlit,
+            keep_inventory: self.rules.keep_inventory,
+            starter_kit: self.starting.starter_kit,
+            tree_density: self.world_gen.tree_density,
+            knockback_strength: self.combat.knockback_strength,
+            damage_exhaustion: self.combat.damage_exhaustion,
+            physics_tick_rate: self.fixed_time.timestep().as_secs_f64().recip(),
+            crosshair_style: self.crosshair.style,
+            crosshair_size: self.crosshair.size,
+            crosshair_thickness: self.crosshair.thickness,
+            crosshair_color: self.crosshair.color,
+        }
+    }
+}
+
+/// The resources `Settings` is loaded into.
+#[derive(SystemParam)]
+pub struct SettingsTargets<'w> {
+    post_process: ResMut<'w, PostProcessSettings>,
+    lighting: ResMut<'w, BlockLighting>,
+    rules: ResMut<'w, GameRules>,
+    starting: ResMut<'w, StartingInventory>,
+    world_gen: ResMut<'w, WorldGenConfig>,
+    combat: ResMut<'w, CombatSettings>,
+    fixed_time: ResMut<'w, Time<Fixed>>,
+    crosshair: ResMut<'w, CrosshairSettings>,
+}
+
+impl SettingsTargets<'_> {
+    fn apply(&mut self, settings: &Settings) {
+        self.post_process.bloom = settings.bloom;
+        self.lighting.unlit = settings.unlit_blocks;
+        self.rules.keep_inventory = settings.keep_inventory;
+        self.starting.starter_kit = settings.starter_kit;
+        self.world_gen.tree_density = settings.tree_density;
+        self.combat.knockback_strength = settings.knockback_strength;
+        self.combat.damage_exhaustion = settings.damage_exhaustion;
+        self.fixed_time.set_timestep_hz(settings.physics_tick_rate);
+        self.crosshair.style = settings.crosshair_style;
+        self.crosshair.size = settings.crosshair_size;
+        self.crosshair.thickness = settings.crosshair_thickness;
+        self.crosshair.color = settings.crosshair_color;
+    }
+}
+
+/// Runs first in `Startup`, so the world, inventory and crosshair are built
+/// from the saved values.
+pub fn load_settings(mut targets: SettingsTargets) {
+    targets.apply(&Settings::load());
+}
+
+/// Writes `SETTINGS_PATH` whenever a setting differs from what was last
+/// saved or loaded.
+pub fn save_settings(current: CurrentSettings, mut saved: Local<Option<Settings>>) {
+    let settings = current.snapshot();
+    let Some(previous) = saved.as_ref() else {
+        *saved = Some(settings);
+        return;
+    };
+    if *previous == settings {
+        return;
+    }
+    if let Err(err) = settings.save() {
+        warn!("Failed to save settings: {err}");
+    }
+    *saved = Some(settings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crosshair_round_trips_through_the_settings_file() {
+        let settings = Settings {
+            crosshair_style: CrosshairStyle::Circle,
+            crosshair_size: 24.0,
+            crosshair_thickness: 3.0,
+            crosshair_color: Color::srgb(1.0, 0.0, 0.0),
+            ..Settings::default()
+        };
+        let parsed = Settings::parse(&settings.contents());
+        assert_eq!(parsed.crosshair_style, CrosshairStyle::Circle);
+        assert_eq!(parsed.crosshair_size, 24.0);
+        assert_eq!(parsed.crosshair_thickness, 3.0);
+        assert_eq!(
+            parsed.crosshair_color.to_srgba().to_hex(),
+            settings.crosshair_color.to_srgba().to_hex()
+        );
+    }
+
+    #[test]
+    fn bad_crosshair_lines_keep_the_defaults() {
+        let parsed = Settings::parse("crosshair_style star\ncrosshair_size -4\n");
+        assert_eq!(parsed.crosshair_style, CrosshairSettings::default().style);
+        assert_eq!(parsed.crosshair_size, CrosshairSettings::default().size);
+    }
+}
@@ -1,6 +1,33 @@
-use crate::components::{Health, Hunger, Player, Stamina};
-use crate::resources::HungerDepleted;
+use crate::components::{
+    CollisionBox, DroppedStack, Grounded, Health, Hunger, IFrames, Knockback, Player, Stamina,
+    Velocity,
+};
+use crate::resources::{
+    CombatSettings, CrosshairSettings, CrosshairStyle, CubeMesh, DamageCause, GameRules,
+    HungerDepleted, Inventory, LifeStats, MaterialHandles, MiningState, PlayerDamaged, SpawnPoint,
+    StartingInventory, VoxelWorld,
+};
+use crate::systems::physics::apply_knockback;
 use bevy::prelude::*;
+use rand::Rng;
+
+pub const BUTTON_NORMAL: Color = Color::srgb(0.3, 0.3, 0.3);
+pub const BUTTON_HOVERED: Color = Color::srgb(0.5, 0.5, 0.5);
+pub const BUTTON_PRESSED: Color = Color::srgb(0.2, 0.2, 0.2);
+pub const DANGER_BUTTON_NORMAL: Color = Color::srgb(0.45, 0.1, 0.1);
+pub const DANGER_BUTTON_HOVERED: Color = Color::srgb(0.65, 0.15, 0.15);
+pub const DANGER_BUTTON_PRESSED: Color = Color::srgb(0.3, 0.05, 0.05);
+const DROPPED_STACK_SIZE: f32 = 0.3;
+const DROPPED_STACK_PICKUP_RANGE: f32 = 1.5;
+const DAMAGE_FLASH_PER_POINT: f32 = 0.15;
+const DAMAGE_FLASH_FADE: f32 = 1.5; // per second
+const IFRAME_DURATION: f32 = 0.5;
+const LOW_HEALTH: f32 = 25.0;
+const HUNGER_DECAY_RATE: f32 = 0.5; // while walking at WALK_SPEED
+const WALK_SPEED: f32 = 5.0;
+const HUNGER_IDLE_FACTOR: f32 = 0.25; // of the walking rate, standing still on the ground
+const HUNGER_AIRBORNE_FACTOR: f32 = 0.5; // added while jumping or falling
+const HUNGER_MINING_FACTOR: f32 = 0.5; // added while holding left click on a block
 
 #[derive(Component)]
 pub struct HealthBar;
@@ -17,6 +44,9 @@ pub struct InventoryUI;
 #[derive(Component)]
 pub struct InventoryText;
 
+#[derive(Component)]
+pub struct Crosshair;
+
 #[derive(Component)]
 pub struct FpsText;
 
@@ -32,13 +62,60 @@ pub struct DeathScreen;
 #[derive(Component)]
 pub struct RespawnButton;
 
+#[derive(Component)]
+pub struct QuitButton;
+
+/// Red screen edge that flashes on damage and pulses at low health.
+/// `flash` fades back to zero after each hit.
+#[derive(Component, Default)]
+pub struct DamageVignette {
+    pub flash: f32,
+}
+
+#[derive(Component)]
+pub struct DeathDetailsText;
+
+/// Background colors applied by `button_interaction_system`.
+#[derive(Component, Clone, Copy)]
+pub struct ButtonPalette {
+    pub normal: Color,
+    pub hovered: Color,
+    pub pressed: Color,
+}
+
+impl ButtonPalette {
+    pub const DEFAULT: Self = Self {
+        normal: BUTTON_NORMAL,
+        hovered: BUTTON_HOVERED,
+        pressed: BUTTON_PRESSED,
+    };
+
+    pub const DANGER: Self = Self {
+        normal: DANGER_BUTTON_NORMAL,
+        hovered: DANGER_BUTTON_HOVERED,
+        pressed: DANGER_BUTTON_PRESSED,
+    };
+}
+
+/// Drains hunger faster the busier the player is: barely while standing
+/// still, at HUNGER_DECAY_RATE while walking, and more on top of that while
+/// moving faster, jumping or mining.
 pub fn hunger_decay(
-    mut query: Query<&mut Hunger>,
+    mut query: Query<(&mut Hunger, &Velocity, &Grounded)>,
     time: Res<Time>,
+    mining: Res<MiningState>,
     mut events: EventWriter<HungerDepleted>,
 ) {
-    for mut hunger in query.iter_mut() {
-        hunger.0 -= 0.5 * time.delta_secs();
+    for (mut hunger, velocity, grounded) in query.iter_mut() {
+        let walking = (velocity.xz().length() / WALK_SPEED).min(2.0);
+        let mut activity = HUNGER_IDLE_FACTOR + walking * (1.0 - HUNGER_IDLE_FACTOR);
+        if !grounded.0 {
+            activity += HUNGER_AIRBORNE_FACTOR;
+        }
+        if mining.target.is_some() {
+            activity += HUNGER_MINING_FACTOR;
+        }
+        hunger.0 -= HUNGER_DECAY_RATE * activity * time.delta_secs();
         if hunger.0 <= 0.0 {
             hunger.0 = 0.0;
             events.send(HungerDepleted);
@@ -48,17 +125,107 @@ pub fn hunger_decay(
 
 pub fn starvation_damage(
     mut events: EventReader<HungerDepleted>,
-    mut query: Query<&mut Health>,
+    mut damage_events: EventWriter<PlayerDamaged>,
     time: Res<Time>,
 ) {
     for _ in events.read() {
-        for mut health in query.iter_mut() {
-            health.0 -= 5.0 * time.delta_secs();
+        damage_events.send(PlayerDamaged {
+            amount: 5.0 * time.delta_secs(),
+            cause: DamageCause::Starvation,
+            source: None,
+        });
+    }
+}
+
+/// Player parts a hit touches: health and hunger, plus the knockback and
+/// the i-frames that gate it.
+type DamagedPlayer = (
+    &'static Transform,
+    &'static mut Health,
+    &'static mut Hunger,
+    &'static mut Velocity,
+    &'static mut Knockback,
+    &'static mut IFrames,
+);
+
+pub fn apply_player_damage(
+    time: Res<Time>,
+    combat: Res<CombatSettings>,
+    mut events: EventReader<PlayerDamaged>,
+    mut player_query: Query<DamagedPlayer, With<Player>>,
+    mut vignette_query: Query<&mut DamageVignette>,
+    mut life_stats: ResMut<LifeStats>,
+) {
+    let Ok((transform, mut health, mut hunger, mut velocity, mut knockback, mut iframes)) =
+        player_query.get_single_mut()
+    else {
+        return;
+    };
+    iframes.0 = (iframes.0 - time.delta_secs()).max(0.0);
+    for event in events.read() {
+        health.0 -= event.amount;
+        life_stats.last_damage = Some(event.cause);
+        // Getting hurt tires the player out, except for the starving itself
+        if event.cause != DamageCause::Starvation {
+            hunger.0 = (hunger.0 - event.amount * combat.damage_exhaustion).max(0.0);
+        }
+        if let Some(source) = event.source.filter(|_| iframes.0 <= 0.0) {
+            apply_knockback(
+                &mut velocity,
+                &mut knockback,
+                source,
+                transform.translation,
+                combat.knockback_strength,
+            );
+            iframes.0 = IFRAME_DURATION;
+        }
+        if let Ok(mut vignette) = vignette_query.get_single_mut() {
+            vignette.flash = (vignette.flash + event.amount * DAMAGE_FLASH_PER_POINT).min(1.0);
         }
     }
 }
 
-pub fn setup_ui(mut commands: Commands) {
+pub fn setup_damage_vignette(mut commands: Commands) {
+    commands.spawn((
+        DamageVignette::default(),
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            position_type: PositionType::Absolute,
+            border: UiRect::all(Val::Px(40.0)),
+            ..default()
+        },
+        BorderColor(Color::NONE),
+        PickingBehavior::IGNORE,
+    ));
+}
+
+pub fn update_damage_vignette(
+    time: Res<Time>,
+    player_query: Query<&Health, With<Player>>,
+    mut vignette_query: Query<(&mut DamageVignette, &mut BorderColor)>,
+) {
+    let Ok((mut vignette, mut border)) = vignette_query.get_single_mut() else {
+        return;
+    };
+    vignette.flash = (vignette.flash - DAMAGE_FLASH_FADE * time.delta_secs()).max(0.0);
+
+    let low_health = player_query
+        .get_single()
+        .is_ok_and(|health| health.0 > 0.0 && health.0 < LOW_HEALTH);
+    let pulse = if low_health {
+        0.15 + 0.1 * (time.elapsed_secs() * 4.0).sin()
+    } else {
+        0.0
+    };
+    border.0 = Color::srgba(0.8, 0.0, 0.0, vignette.flash.max(pulse) * 0.6);
+}
+
+pub fn track_survival_time(mut life_stats: ResMut<LifeStats>, time: Res<Time>) {
+    life_stats.time_alive += time.delta_secs();
+}
+
+pub fn setup_ui(mut commands: Commands, crosshair: Res<CrosshairSettings>) {
     // Container
     commands
         .spawn((Node {
@@ -250,31 +417,115 @@ pub fn setup_ui(mut commands: Commands) {
                 });
         });
 
-    // Simplified Crosshair (Minimal Dot)
+    spawn_crosshair(&mut commands, &crosshair);
+}
+
+fn spawn_crosshair(commands: &mut Commands, settings: &CrosshairSettings) {
+    let size = settings.size;
+    let thickness = settings.thickness;
+    let color = settings.color;
+    let outline = Color::srgba(0.0, 0.0, 0.0, 0.5);
+
     commands
-        .spawn(Node {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            position_type: PositionType::Absolute,
-            justify_content: JustifyContent::Center,
-            align_items: AlignItems::Center,
-            ..default()
-        })
-        .with_children(|parent| {
-            parent.spawn((
-                Node {
-                    width: Val::Px(4.0),
-                    height: Val::Px(4.0),
-                    border: UiRect::all(Val::Px(1.0)),
-                    ..default()
-                },
-                BackgroundColor(Color::srgba(0.0, 1.0, 1.0, 0.8)),
-                BorderColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
-                BorderRadius::all(Val::Px(2.0)),
-            ));
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            Crosshair,
+        ))
+        .with_children(|parent| match settings.style {
+            CrosshairStyle::Dot => {
+                parent.spawn((
+                    Node {
+                        width: Val::Px(thickness * 2.0),
+                        height: Val::Px(thickness * 2.0),
+                        border: UiRect::all(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BackgroundColor(color),
+                    BorderColor(outline),
+                    BorderRadius::all(Val::Px(thickness)),
+                ));
+            }
+            CrosshairStyle::Cross => {
+                parent
+                    .spawn(Node {
+                        width: Val::Px(size),
+                        height: Val::Px(size),
+                        ..default()
+                    })
+                    .with_children(|cross| {
+                        // Four bars around a small gap in the middle
+                        let gap = thickness * 2.0;
+                        let arm = (size - gap) / 2.0;
+                        let center = (size - thickness) / 2.0;
+                        let bars = [
+                            (0.0, center, arm, thickness),
+                            (size - arm, center, arm, thickness),
+                            (center, 0.0, thickness, arm),
+                            (center, size - arm, thickness, arm),
+                        ];
+                        for (left, top, width, height) in bars {
+                            cross.spawn((
+                                Node {
+                                    position_type: PositionType::Absolute,
+                                    left: Val::Px(left),
+                                    top: Val::Px(top),
+                                    width: Val::Px(width),
+                                    height: Val::Px(height),
+                                    ..default()
+                                },
+                                BackgroundColor(color),
+                            ));
+                        }
+                    });
+            }
+            CrosshairStyle::Circle => {
+                parent.spawn((
+                    Node {
+                        width: Val::Px(size),
+                        height: Val::Px(size),
+                        border: UiRect::all(Val::Px(thickness)),
+                        ..default()
+                    },
+                    BorderColor(color),
+                    BorderRadius::MAX,
+                ));
+            }
         });
 }
 
+/// F9 cycles the crosshair style.
+pub fn cycle_crosshair_style(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<CrosshairSettings>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F9) {
+        settings.style = settings.style.next();
+    }
+}
+
+/// Respawns the crosshair whenever its settings change after startup.
+pub fn rebuild_crosshair(
+    mut commands: Commands,
+    settings: Res<CrosshairSettings>,
+    crosshair_query: Query<Entity, With<Crosshair>>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    for entity in crosshair_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_crosshair(&mut commands, &settings);
+}
+
 pub fn setup_pause_menu(mut commands: Commands) {
     commands
         .spawn((
@@ -359,36 +610,73 @@ pub fn setup_death_screen(mut commands: Commands) {
                 TextColor(Color::srgb(1.0, 0.1, 0.15)),
             ));
 
-            parent
-                .spawn((
-                    RespawnButton,
-                    Button,
-                    Node {
-                        width: Val::Px(260.0),
-                        height: Val::Px(75.0),
-                        margin: UiRect::top(Val::Px(50.0)),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        border: UiRect::all(Val::Px(2.0)),
-                        ..default()
-                    },
-                    BackgroundColor(Color::srgba(0.2, 0.2, 0.2, 0.8)),
-                    BorderColor(Color::WHITE),
-                    BorderRadius::all(Val::Px(12.0)),
-                ))
-                .with_children(|p| {
-                    p.spawn((
-                        Text::new("RESPAWN"),
-                        TextFont {
-                            font_size: 34.0,
-                            ..default()
-                        },
-                        TextColor(Color::WHITE),
-                    ));
-                });
+            parent.spawn((
+                DeathDetailsText,
+                Text::new(""),
+                TextFont {
+                    font_size: 22.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.85, 0.85, 0.85)),
+                TextLayout::new_with_justify(JustifyText::Center),
+            ));
+
+            spawn_death_button(parent, RespawnButton, "RESPAWN", ButtonPalette::DEFAULT);
+            spawn_death_button(parent, QuitButton, "QUIT GAME", ButtonPalette::DANGER);
         });
 }
 
+fn spawn_death_button(
+    parent: &mut ChildBuilder,
+    marker: impl Component,
+    label: &str,
+    palette: ButtonPalette,
+) {
+    parent
+        .spawn((
+            marker,
+            Button,
+            Node {
+                width: Val::Px(260.0),
+                height: Val::Px(75.0),
+                margin: UiRect::top(Val::Px(30.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                border: UiRect::all(Val::Px(2.0)),
+                ..default()
+            },
+            BackgroundColor(palette.normal),
+            BorderColor(Color::WHITE),
+            BorderRadius::all(Val::Px(12.0)),
+            palette,
+        ))
+        .with_children(|p| {
+            p.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 34.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        });
+}
+
+pub fn button_interaction_system(
+    mut query: Query<
+        (&Interaction, &ButtonPalette, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, palette, mut color) in query.iter_mut() {
+        color.0 = match *interaction {
+            Interaction::Pressed => palette.pressed,
+            Interaction::Hovered => palette.hovered,
+            Interaction::None => palette.normal,
+        };
+    }
+}
+
 pub fn update_pause_menu_visibility(
     state: Res<State<crate::resources::GameState>>,
     mut query: Query<&mut Node, With<PauseMenu>>,
@@ -404,6 +692,9 @@ pub fn update_pause_menu_visibility(
 pub fn update_death_screen(
     player_query: Query<&Health, With<Player>>,
     mut death_screen_query: Query<&mut Node, With<DeathScreen>>,
+    mut details_query: Query<&mut Text, With<DeathDetailsText>>,
+    life_stats: Res<LifeStats>,
+    rules: Res<GameRules>,
     mut next_state: ResMut<NextState<crate::resources::GameState>>,
     state: Res<State<crate::resources::GameState>>,
 ) {
@@ -414,6 +705,26 @@ pub fn update_death_screen(
             }
             if *state.get() != crate::resources::GameState::GameOver {
                 next_state.set(crate::resources::GameState::GameOver);
+
+                if let Ok(mut text) = details_query.get_single_mut() {
+                    let cause = life_stats
+                        .last_damage
+                        .map(|cause| cause.description())
+                        .unwrap_or("Died");
+                    let seconds = life_stats.time_alive as u32;
+                    let items = if rules.keep_inventory {
+                        "Inventory kept"
+                    } else {
+                        "Items dropped where you fell"
+                    };
+                    text.0 = format!(
+                        "{}\nSurvived {}m {:02}s\n{}",
+                        cause,
+                        seconds / 60,
+                        seconds % 60,
+                        items
+                    );
+                }
             }
         } else {
             if let Ok(mut node) = death_screen_query.get_single_mut() {
@@ -423,33 +734,135 @@ pub fn update_death_screen(
     }
 }
 
+/// Player state reset by `respawn_system`; the collision box sizes the
+/// search for a free spot.
+type RespawnedPlayer = (
+    &'static mut Health,
+    &'static mut Hunger,
+    &'static mut Stamina,
+    &'static mut Transform,
+    &'static mut Velocity,
+    &'static CollisionBox,
+);
+
+pub fn toggle_keep_inventory(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut rules: ResMut<GameRules>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F7) {
+        rules.keep_inventory = !rules.keep_inventory;
+        info!("Keep inventory: {}", rules.keep_inventory);
+    }
+}
+
+/// Empties the inventory into small block cubes popped out around the
+/// player on death, unless `keep_inventory` is on.
+pub fn drop_inventory_on_death(
+    mut commands: Commands,
+    rules: Res<GameRules>,
+    mut inventory: ResMut<Inventory>,
+    player_query: Query<&Transform, With<Player>>,
+    cube_mesh: Res<CubeMesh>,
+    materials: Res<MaterialHandles>,
+) {
+    if rules.keep_inventory {
+        return;
+    }
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    let mut rng = rand::rng();
+    for (block_type, count) in inventory.items.drain() {
+        if count == 0 {
+            continue;
+        }
+        let angle = rng.random_range(0.0..std::f32::consts::TAU);
+        commands.spawn((
+            Mesh3d(cube_mesh.0.clone()),
+            MeshMaterial3d(materials.get(block_type)),
+            Transform::from_translation(player_transform.translation)
+                .with_scale(Vec3::splat(DROPPED_STACK_SIZE)),
+            DroppedStack { block_type, count },
+            Velocity(Vec3::new(angle.cos() * 2.0, 3.0, angle.sin() * 2.0)),
+            Grounded(false),
+            CollisionBox::cube(DROPPED_STACK_SIZE),
+        ));
+    }
+}
+
+pub fn pickup_dropped_stacks(
+    mut commands: Commands,
+    player_query: Query<&Transform, With<Player>>,
+    stack_query: Query<(Entity, &Transform, &DroppedStack)>,
+    mut inventory: ResMut<Inventory>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    for (entity, transform, stack) in stack_query.iter() {
+        if transform.translation.distance(player_transform.translation) < DROPPED_STACK_PICKUP_RANGE
+        {
+            *inventory.items.entry(stack.block_type).or_insert(0) += stack.count;
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+pub fn give_starting_inventory(
+    starting: Res<StartingInventory>,
+    mut inventory: ResMut<crate::resources::Inventory>,
+) {
+    if !starting.starter_kit {
+        return;
+    }
+    for &(block_type, count) in &starting.kit {
+        *inventory.items.entry(block_type).or_insert(0) += count;
+    }
+}
+
 pub fn respawn_system(
-    mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor),
-        (Changed<Interaction>, With<RespawnButton>),
-    >,
-    mut player_query: Query<(&mut Health, &mut Hunger, &mut Stamina, &mut Transform), With<Player>>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<RespawnButton>)>,
+    mut player_query: Query<RespawnedPlayer, With<Player>>,
+    mut life_stats: ResMut<LifeStats>,
+    spawn_point: Res<SpawnPoint>,
+    world: Res<VoxelWorld>,
     mut next_state: ResMut<NextState<crate::resources::GameState>>,
 ) {
-    for (interaction, mut color) in interaction_query.iter_mut() {
-        match *interaction {
-            Interaction::Pressed => {
-                if let Ok((mut health, mut hunger, mut stamina, mut transform)) =
-                    player_query.get_single_mut()
-                {
-                    health.0 = 100.0;
-                    hunger.0 = 100.0;
-                    stamina.0 = 100.0;
-                    transform.translation = Vec3::new(0.0, 10.0, 0.0);
-                    next_state.set(crate::resources::GameState::InGame);
-                }
-            }
-            Interaction::Hovered => {
-                *color = BackgroundColor(Color::srgb(0.5, 0.5, 0.5));
-            }
-            Interaction::None => {
-                *color = BackgroundColor(Color::srgb(0.3, 0.3, 0.3));
-            }
+    for interaction in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Ok((
+            mut health,
+            mut hunger,
+            mut stamina,
+            mut transform,
+            mut velocity,
+            collision_box,
+        )) = player_query.get_single_mut()
+        {
+            health.0 = 100.0;
+            hunger.0 = 100.0;
+            stamina.0 = 100.0;
+            transform.translation = spawn_point.safe_position(&world, collision_box.half_height);
+            // Drop any momentum carried over from the corpse sliding around
+            velocity.0 = Vec3::ZERO;
+            *life_stats = LifeStats::default();
+            next_state.set(crate::resources::GameState::InGame);
+        }
+    }
+}
+
+/// The death screen's quit button closes the game, like Q on the pause
+/// menu; there's no main menu to go back to.
+pub fn quit_button_system(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<QuitButton>)>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for interaction in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            exit.send(AppExit::Success);
         }
     }
 }
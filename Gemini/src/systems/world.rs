@@ -1,5 +1,9 @@
 use crate::components::{BlockMarker, BlockType, MainCamera};
-use crate::resources::{CubeMesh, MaterialHandles, RaycastHit, VoxelWorld};
+use crate::resources::{
+    BlockChanged, CubeMesh, MaterialHandles, MiningState, RaycastHit, SpawnPoint, VoxelWorld,
+    WorldGenConfig, WorldSeed,
+};
+use crate::systems::save::{LoadedSave, SaveGame};
 use bevy::pbr::{MaterialPipeline, MaterialPipelineKey, NotShadowCaster};
 use bevy::prelude::*;
 use bevy::reflect::TypePath;
@@ -14,7 +18,7 @@ pub struct SelectionMaterial {
 
 impl Material for SelectionMaterial {
     fn fragment_shader() -> ShaderRef {
-        "shaders/selection.wgsl".into()
+        crate::systems::loading::SELECTION_SHADER_PATH.into()
     }
 
     fn alpha_mode(&self) -> AlphaMode {
@@ -34,27 +38,22 @@ pub fn init_assets(
     let material_handles = MaterialHandles {
         grass: standard_materials.add(StandardMaterial {
             base_color: Color::srgb(0.22, 0.48, 0.32),
-            unlit: true,
             ..default()
         }),
         dirt: standard_materials.add(StandardMaterial {
             base_color: Color::srgb(0.38, 0.26, 0.18),
-            unlit: true,
             ..default()
         }),
         stone: standard_materials.add(StandardMaterial {
             base_color: Color::srgb(0.42, 0.45, 0.48),
-            unlit: true,
             ..default()
         }),
         wood: standard_materials.add(StandardMaterial {
             base_color: Color::srgb(0.32, 0.18, 0.12),
-            unlit: true,
             ..default()
         }),
         leaves: standard_materials.add(StandardMaterial {
             base_color: Color::srgb(0.12, 0.42, 0.22),
-            unlit: true,
             ..default()
         }),
     };
@@ -76,20 +75,95 @@ pub fn init_assets(
 #[derive(Component)]
 pub struct SelectionBox;
 
+pub fn toggle_block_lighting(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<crate::resources::BlockLighting>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F8) {
+        settings.unlit = !settings.unlit;
+    }
+}
+
+/// Switches every block material between lit shading, which follows the
+/// day/night sun, and the flat unlit look.
+pub fn apply_block_lighting(
+    settings: Res<crate::resources::BlockLighting>,
+    handles: Res<MaterialHandles>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for handle in handles.all() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.unlit = settings.unlit;
+        }
+    }
+}
+
+/// Loads the save at `SAVE_PATH` if there's a usable one, otherwise
+/// generates fresh terrain from the seed.
 pub fn setup_world(
     mut commands: Commands,
     cube_mesh: Res<CubeMesh>,
     materials: Res<MaterialHandles>,
+    world_gen: Res<WorldGenConfig>,
+    seed: Res<WorldSeed>,
+    mut spawn_point: ResMut<SpawnPoint>,
 ) {
     let mut world = VoxelWorld::default();
+    match SaveGame::load() {
+        Some(save) => {
+            world.blocks = save.world_blocks();
+            spawn_exposed_blocks(&mut commands, &mut world, &cube_mesh, &materials);
+            commands.insert_resource(LoadedSave(save));
+        }
+        None => {
+            info!("World seed: {}", seed.0);
+            generate_world(
+                &mut commands,
+                &mut world,
+                &cube_mesh,
+                &materials,
+                &world_gen,
+                *seed,
+                &mut spawn_point,
+            );
+        }
+    }
+    commands.insert_resource(world);
 
+    // Sun/Moon Light
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 10000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_xyz(10.0, 20.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+        Sun,
+    ));
+}
+
+/// Hilly terrain and trees from `seed`, with the spawn point moved onto the
+/// hill.
+fn generate_world(
+    commands: &mut Commands,
+    world: &mut VoxelWorld,
+    cube_mesh: &CubeMesh,
+    materials: &MaterialHandles,
+    world_gen: &WorldGenConfig,
+    seed: WorldSeed,
+    spawn_point: &mut SpawnPoint,
+) {
     for x in -16..16 {
         for z in -16..16 {
-            for y in 0..4 {
+            let surface = world_gen.surface_height(seed, x, z);
+            for y in 0..=surface {
                 let coord = IVec3::new(x, y, z);
-                let block_type = if y == 3 {
+                let block_type = if y == surface {
                     BlockType::Grass
-                } else if y > 1 {
+                } else if y >= surface - 2 {
                     BlockType::Dirt
                 } else {
                     BlockType::Stone
@@ -100,7 +174,44 @@ pub fn setup_world(
         }
     }
 
-    // Now spawn entities only for surface blocks (occlusion culling)
+    spawn_exposed_blocks(commands, world, cube_mesh, materials);
+
+    // Stand the player on the hill at spawn rather than inside it
+    let spawn = spawn_point.0.round().as_ivec3();
+    spawn_point.0.y = world_gen.surface_height(seed, spawn.x, spawn.z) as f32 + 2.0;
+
+    // Random Trees, kept off the spawn and apart so canopies don't fuse
+    let mut rng = seed.rng(WorldSeed::TREES);
+    let target = (world_gen.tree_density * 28.0 * 28.0 / 100.0).round() as usize;
+    let spawn = spawn_point.0.round().as_ivec3();
+    let mut trees: Vec<IVec3> = Vec::with_capacity(target);
+    for _ in 0..target * 10 {
+        if trees.len() >= target {
+            break;
+        }
+        let x = rng.random_range(-14..14);
+        let z = rng.random_range(-14..14);
+        let coord = IVec3::new(x, world_gen.surface_height(seed, x, z) + 1, z); // Start above top layer
+        let near_spawn = (x - spawn.x).abs() <= 2 && (z - spawn.z).abs() <= 2;
+        let crowded = trees
+            .iter()
+            .any(|tree| (tree.x - x).abs() < 4 && (tree.z - z).abs() < 4);
+        if near_spawn || crowded {
+            continue;
+        }
+        spawn_tree(coord, commands, &cube_mesh.0, materials, world);
+        trees.push(coord);
+    }
+}
+
+/// Spawns entities only for surface blocks (occlusion culling); buried ones
+/// appear through `update_block_entities` once dug out.
+pub fn spawn_exposed_blocks(
+    commands: &mut Commands,
+    world: &mut VoxelWorld,
+    cube_mesh: &CubeMesh,
+    materials: &MaterialHandles,
+) {
     let block_coords: Vec<IVec3> = world.blocks.keys().cloned().collect();
     for coord in block_coords {
         let mut is_exposed = false;
@@ -122,18 +233,10 @@ pub fn setup_world(
 
         if is_exposed {
             let block_type = world.blocks[&coord];
-            let material = match block_type {
-                BlockType::Grass => materials.grass.clone(),
-                BlockType::Dirt => materials.dirt.clone(),
-                BlockType::Stone => materials.stone.clone(),
-                BlockType::Wood => materials.wood.clone(),
-                BlockType::Leaves => materials.leaves.clone(),
-            };
-
             let entity = commands
                 .spawn((
                     Mesh3d(cube_mesh.0.clone()),
-                    MeshMaterial3d(material),
+                    MeshMaterial3d(materials.get(block_type)),
                     Transform::from_translation(coord.as_vec3()),
                     block_type,
                     BlockMarker(coord),
@@ -143,28 +246,6 @@ pub fn setup_world(
             world.entities.insert(coord, entity);
         }
     }
-
-    // Random Trees
-    let mut rng = rand::rng();
-    for _ in 0..20 {
-        let x = rng.random_range(-14..14);
-        let z = rng.random_range(-14..14);
-        let coord = IVec3::new(x, 4, z); // Start above top layer
-        spawn_tree(coord, &mut commands, &cube_mesh.0, &materials, &mut world);
-    }
-
-    commands.insert_resource(world);
-
-    // Sun/Moon Light
-    commands.spawn((
-        DirectionalLight {
-            illuminance: 10000.0,
-            shadows_enabled: true,
-            ..default()
-        },
-        Transform::from_xyz(10.0, 20.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
-        Sun,
-    ));
 }
 
 #[derive(Component)]
@@ -175,41 +256,41 @@ pub fn day_night_cycle(
     time: Res<Time>,
     mut sun_query: Query<(&mut Transform, &mut DirectionalLight), With<Sun>>,
     mut camera_query: Query<&mut Camera, With<crate::components::MainCamera>>,
+    mut ambient: ResMut<AmbientLight>,
 ) {
-    // Only update once every few frames or keep it simple
-    let day_duration = 60.0;
-    time_of_day.0 += time.delta_secs() / day_duration;
-    if time_of_day.0 > 1.0 {
-        time_of_day.0 -= 1.0;
+    time_of_day.time += time.delta_secs() / time_of_day.day_length_seconds;
+    if time_of_day.time > 1.0 {
+        time_of_day.time -= 1.0;
     }
 
-    let angle = time_of_day.0 * std::f32::consts::TAU;
-    let sun_y = angle.sin();
-
     if let Ok((mut transform, mut light)) = sun_query.get_single_mut() {
-        let rot = Quat::from_rotation_x(angle);
+        let rot = Quat::from_rotation_x(time_of_day.time * std::f32::consts::TAU);
         transform.translation = rot * Vec3::new(0.0, 20.0, 0.0);
         transform.look_at(Vec3::ZERO, Vec3::Y);
 
         // Dynamic light intensity
-        light.illuminance = (sun_y.max(0.0) * 10000.0).max(500.0);
+        light.illuminance = time_of_day.sun_intensity() * 10000.0;
     }
 
     if let Ok(mut camera) = camera_query.get_single_mut() {
-        let sky_color = if sun_y < -0.1 {
-            Color::srgb(0.02, 0.02, 0.05) // Dark Night
-        } else if sun_y < 0.2 {
-            Color::srgb(0.8, 0.4, 0.2) // Sunset/Sunrise
-        } else {
-            Color::srgb(0.5, 0.7, 1.0) // Day
-        };
-        camera.clear_color = ClearColorConfig::Custom(sky_color);
+        camera.clear_color = ClearColorConfig::Custom(time_of_day.sky_color());
+    }
+
+    ambient.color = time_of_day.ambient_color();
+}
+
+/// Hides the selection box while world interaction is suppressed, since
+/// update_targeting isn't running to move it.
+pub fn hide_targeting(mut selection_query: Query<&mut Visibility, With<SelectionBox>>) {
+    for mut visibility in selection_query.iter_mut() {
+        visibility.set_if_neq(Visibility::Hidden);
     }
 }
 
 pub fn update_targeting(
     camera_query: Query<(&GlobalTransform, &Camera), With<MainCamera>>,
     world: Res<VoxelWorld>,
+    mining: Res<MiningState>,
     mut selection_query: Query<(&mut Transform, &mut Visibility), With<SelectionBox>>,
 ) {
     let Ok((cam_transform, _)) = camera_query.get_single() else {
@@ -280,6 +361,14 @@ pub fn update_targeting(
         if hit {
             *visibility = Visibility::Visible;
             selection_transform.translation = map_pos.as_vec3();
+            // Shrinks toward the middle of the block as it's mined
+            let mined = match (mining.target, world.blocks.get(&map_pos)) {
+                (Some(target), Some(block_type)) if target == map_pos => {
+                    (mining.progress / block_type.hardness()).min(1.0)
+                }
+                _ => 0.0,
+            };
+            selection_transform.scale = Vec3::splat(1.0 - 0.6 * mined);
         } else {
             *visibility = Visibility::Hidden;
         }
@@ -344,8 +433,8 @@ pub fn block_raycast(
     world: Res<VoxelWorld>,
     mut raycast_events: EventWriter<RaycastHit>,
 ) {
-    if !mouse_input.just_pressed(MouseButton::Left) && !mouse_input.just_pressed(MouseButton::Right)
-    {
+    // Every frame left is held, so mining can tell the target is still there
+    if !mouse_input.pressed(MouseButton::Left) && !mouse_input.just_pressed(MouseButton::Right) {
         return;
     }
 
@@ -427,60 +516,41 @@ pub fn block_raycast(
 }
 
 pub fn block_modification(
-    mut commands: Commands,
+    time: Res<Time>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     mut raycast_events: EventReader<RaycastHit>,
+    mut block_changed: EventWriter<BlockChanged>,
     mut world: ResMut<VoxelWorld>,
     mut inventory: ResMut<crate::resources::Inventory>,
-    cube_mesh: Res<CubeMesh>,
-    materials: Res<MaterialHandles>,
+    mut mining: ResMut<MiningState>,
 ) {
+    let mut mined_this_frame = false;
     for event in raycast_events.read() {
-        if mouse_input.just_pressed(MouseButton::Left) {
-            // Remove block
+        if mouse_input.pressed(MouseButton::Left) {
+            // Mine the block, breaking it once held on it for its hardness
+            mined_this_frame = true;
+            let Some(&block_type) = world.blocks.get(&event.coord) else {
+                continue;
+            };
+            if mining.target != Some(event.coord) {
+                mining.target = Some(event.coord);
+                mining.progress = 0.0;
+            }
+            mining.progress += time.delta_secs();
+            if mining.progress < block_type.hardness() {
+                continue;
+            }
+            *mining = MiningState::default();
+
             if let Some(block_type) = world.blocks.remove(&event.coord) {
-                if let Some(entity) = world.entities.remove(&event.coord) {
-                    commands.entity(entity).despawn_recursive();
-                }
                 // Add to inventory
                 *inventory.items.entry(block_type).or_insert(0) += 1;
 
-                // Reveal neighbors
-                let neighbors = [
-                    IVec3::new(1, 0, 0),
-                    IVec3::new(-1, 0, 0),
-                    IVec3::new(0, 1, 0),
-                    IVec3::new(0, -1, 0),
-                    IVec3::new(0, 0, 1),
-                    IVec3::new(0, 0, -1),
-                ];
-
-                for offset in neighbors {
-                    let neighbor_coord = event.coord + offset;
-                    if let Some(&neighbor_type) = world.blocks.get(&neighbor_coord) {
-                        if !world.entities.contains_key(&neighbor_coord) {
-                            let material = match neighbor_type {
-                                BlockType::Grass => materials.grass.clone(),
-                                BlockType::Dirt => materials.dirt.clone(),
-                                BlockType::Stone => materials.stone.clone(),
-                                BlockType::Wood => materials.wood.clone(),
-                                BlockType::Leaves => materials.leaves.clone(),
-                            };
-
-                            let entity = commands
-                                .spawn((
-                                    Mesh3d(cube_mesh.0.clone()),
-                                    MeshMaterial3d(material),
-                                    Transform::from_translation(neighbor_coord.as_vec3()),
-                                    neighbor_type,
-                                    BlockMarker(neighbor_coord),
-                                    NotShadowCaster,
-                                ))
-                                .id();
-                            world.entities.insert(neighbor_coord, entity);
-                        }
-                    }
-                }
+                block_changed.send(BlockChanged {
+                    coord: event.coord,
+                    old: Some(block_type),
+                    new: None,
+                });
             }
         } else if mouse_input.just_pressed(MouseButton::Right) {
             // Add block from inventory
@@ -495,27 +565,7 @@ pub fn block_modification(
             if let Some(block_type) = available_block {
                 let new_pos = event.coord + event.normal;
                 if !world.blocks.contains_key(&new_pos) {
-                    let material = match block_type {
-                        BlockType::Grass => materials.grass.clone(),
-                        BlockType::Dirt => materials.dirt.clone(),
-                        BlockType::Stone => materials.stone.clone(),
-                        BlockType::Wood => materials.wood.clone(),
-                        BlockType::Leaves => materials.leaves.clone(),
-                    };
-
-                    let entity = commands
-                        .spawn((
-                            Mesh3d(cube_mesh.0.clone()),
-                            MeshMaterial3d(material),
-                            Transform::from_translation(new_pos.as_vec3()),
-                            block_type,
-                            BlockMarker(new_pos),
-                            NotShadowCaster,
-                        ))
-                        .id();
-
                     world.blocks.insert(new_pos, block_type);
-                    world.entities.insert(new_pos, entity);
 
                     // Consume from inventory
                     if let Some(count) = inventory.items.get_mut(&block_type) {
@@ -523,8 +573,72 @@ pub fn block_modification(
                             *count -= 1;
                         }
                     }
+
+                    block_changed.send(BlockChanged {
+                        coord: new_pos,
+                        old: None,
+                        new: Some(block_type),
+                    });
+                }
+            }
+        }
+    }
+    if !mined_this_frame && mining.target.is_some() {
+        *mining = MiningState::default();
+    }
+}
+
+/// Keeps block entities in step with `VoxelWorld::blocks`: replaces the
+/// entity of a changed cell and reveals neighbors hidden behind a removed one.
+pub fn update_block_entities(
+    mut commands: Commands,
+    mut block_changed: EventReader<BlockChanged>,
+    mut world: ResMut<VoxelWorld>,
+    cube_mesh: Res<CubeMesh>,
+    materials: Res<MaterialHandles>,
+) {
+    let neighbors = [
+        IVec3::new(1, 0, 0),
+        IVec3::new(-1, 0, 0),
+        IVec3::new(0, 1, 0),
+        IVec3::new(0, -1, 0),
+        IVec3::new(0, 0, 1),
+        IVec3::new(0, 0, -1),
+    ];
+
+    for event in block_changed.read() {
+        let replaced = event.old.and_then(|_| world.entities.remove(&event.coord));
+        if let Some(entity) = replaced {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        let mut to_spawn = Vec::new();
+        match event.new {
+            Some(block_type) => to_spawn.push((event.coord, block_type)),
+            None => {
+                for offset in neighbors {
+                    let neighbor_coord = event.coord + offset;
+                    if let Some(&neighbor_type) = world.blocks.get(&neighbor_coord)
+                        && !world.entities.contains_key(&neighbor_coord)
+                    {
+                        to_spawn.push((neighbor_coord, neighbor_type));
+                    }
                 }
             }
         }
+
+        for (coord, block_type) in to_spawn {
+            let entity = commands
+                .spawn((
+                    Mesh3d(cube_mesh.0.clone()),
+                    MeshMaterial3d(materials.get(block_type)),
+                    Transform::from_translation(coord.as_vec3()),
+                    block_type,
+                    BlockMarker(coord),
+                    NotShadowCaster,
+                ))
+                .id();
+            world.entities.insert(coord, entity);
+        }
     }
 }
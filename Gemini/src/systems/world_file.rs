@@ -0,0 +1,291 @@
+//! F10/F12 export and import of the world file shared with the Opus build.
+//! The format itself lives in the `world_file` crate under `shared/`, which
+//! both games depend on, along with the coordinate convention it uses.
+
+use crate::components::{BlockType, CollisionBox, Health, Hunger, Player, Velocity};
+use crate::resources::{CubeMesh, MaterialHandles, VoxelWorld};
+use crate::systems::world::spawn_exposed_blocks;
+use bevy::prelude::*;
+use std::fs;
+use world_file::{PlayerState, WorldFile, world_file_path};
+
+/// Gemini centres blocks on integer coordinates, while the world file puts
+/// a block's cell between `coord` and `coord + 1`. Adding this takes a
+/// position into the file's space.
+const BLOCK_CENTER_OFFSET: Vec3 = Vec3::splat(0.5);
+const IMPORT_REPORT_SECONDS: f32 = 8.0;
+
+/// What the last import brought in, shown on screen for
+/// IMPORT_REPORT_SECONDS so blocks that became stone don't go unnoticed.
+#[derive(Resource, Default)]
+pub struct ImportReport {
+    pub message: String,
+    pub timer: f32,
+}
+
+#[derive(Component)]
+pub struct ImportReportText;
+
+/// Player parts an imported world file sets.
+type ImportedPlayer = (
+    &'static mut Transform,
+    &'static mut Velocity,
+    &'static CollisionBox,
+    &'static mut Health,
+    &'static mut Hunger,
+);
+
+/// The world with the player centred at `translation`, as the shared
+/// world file has it.
+fn world_file_from(
+    world: &VoxelWorld,
+    translation: Vec3,
+    half_height: f32,
+    health: f32,
+    hunger: f32,
+) -> WorldFile {
+    let blocks = world
+        .blocks
+        .iter()
+        .map(|(&coord, block_type)| (coord, block_type.file_name().to_string()))
+        .collect();
+    let player = PlayerState {
+        feet: translation - Vec3::Y * half_height + BLOCK_CENTER_OFFSET,
+        health,
+        hunger,
+    };
+    WorldFile::new("gemini", player, blocks)
+}
+
+/// Where the player's centre goes to stand where `file` says.
+fn player_translation(file: &WorldFile, half_height: f32) -> Vec3 {
+    file.player.feet - BLOCK_CENTER_OFFSET + Vec3::Y * half_height
+}
+
+/// What the player is told after importing `file`, naming the block types
+/// that were loaded as stone.
+fn import_message(file: &WorldFile, unknown: &[String]) -> String {
+    let mut message = format!("Imported {} blocks from {}", file.blocks.len(), file.source);
+    if !unknown.is_empty() {
+        message += &format!(
+            "\nNot in this game, loaded as stone: {}",
+            unknown.join(", ")
+        );
+    }
+    message
+}
+
+/// F10 writes the current world and the player's position, health and
+/// hunger to `world_file_path`.
+pub fn export_world(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    world: Res<VoxelWorld>,
+    player_query: Query<(&Transform, &CollisionBox, &Health, &Hunger), With<Player>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F10) {
+        return;
+    }
+    let file = match player_query.get_single() {
+        Ok((transform, collision_box, health, hunger)) => world_file_from(
+            &world,
+            transform.translation,
+            collision_box.half_height,
+            health.0,
+            hunger.0,
+        ),
+        Err(_) => world_file_from(&world, Vec3::ZERO, 0.0, 100.0, 100.0),
+    };
+    let path = world_file_path();
+    match fs::write(&path, file.serialize()) {
+        Ok(()) => info!(
+            "Exported {} blocks to {}",
+            file.blocks.len(),
+            path.display()
+        ),
+        Err(err) => warn!("Failed to export world: {err}"),
+    }
+}
+
+/// F12 replaces the world with the one in `world_file_path`. Blocks this
+/// game doesn't have become stone, and the import report lists their
+/// names on screen afterwards.
+pub fn import_world(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut commands: Commands,
+    mut world: ResMut<VoxelWorld>,
+    cube_mesh: Res<CubeMesh>,
+    materials: Res<MaterialHandles>,
+    mut player_query: Query<ImportedPlayer, With<Player>>,
+    mut report: ResMut<ImportReport>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F12) {
+        return;
+    }
+    let file = match fs::read_to_string(world_file_path())
+        .map_err(|err| err.to_string())
+        .and_then(|contents| WorldFile::parse(&contents))
+    {
+        Ok(file) => file,
+        Err(err) => {
+            warn!("Failed to import world: {err}");
+            report.message = format!("World import failed: {err}");
+            report.timer = IMPORT_REPORT_SECONDS;
+            return;
+        }
+    };
+
+    for (_, entity) in world.entities.drain() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let (blocks, unknown) = file.resolve_blocks(BlockType::from_file_name, BlockType::Stone);
+    world.blocks = blocks.into_iter().collect();
+    spawn_exposed_blocks(&mut commands, &mut world, &cube_mesh, &materials);
+
+    if let Ok((mut transform, mut velocity, collision_box, mut health, mut hunger)) =
+        player_query.get_single_mut()
+    {
+        transform.translation = player_translation(&file, collision_box.half_height);
+        velocity.0 = Vec3::ZERO;
+        health.0 = file.player.health;
+        hunger.0 = file.player.hunger;
+    }
+
+    info!("Imported {} blocks from {}", file.blocks.len(), file.source);
+    if !unknown.is_empty() {
+        warn!(
+            "Unknown block types loaded as stone: {}",
+            unknown.join(", ")
+        );
+    }
+    report.message = import_message(&file, &unknown);
+    report.timer = IMPORT_REPORT_SECONDS;
+}
+
+pub fn setup_import_report(mut commands: Commands) {
+    commands.spawn((
+        ImportReportText,
+        Text::new(""),
+        TextFont {
+            font_size: 18.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.9, 0.5)),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(80.0),
+            width: Val::Percent(100.0),
+            ..default()
+        },
+        Visibility::Hidden,
+    ));
+}
+
+/// Shows the import report until its timer runs out.
+pub fn update_import_report(
+    time: Res<Time>,
+    mut report: ResMut<ImportReport>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<ImportReportText>>,
+) {
+    let Ok((mut text, mut visibility)) = text_query.get_single_mut() else {
+        return;
+    };
+    if report.timer <= 0.0 {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    report.timer -= time.delta_secs();
+    if text.0 != report.message {
+        text.0 = report.message.clone();
+    }
+    visibility.set_if_neq(Visibility::Inherited);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An Opus export with every block Gemini has, plus two it doesn't.
+    const OPUS_EXPORT: &str = "voxel-world 3\nsource opus\nplayer 0.5 1 0.5\n\
+                               health 70\nhunger 35.5\nchunk 0 0 0\n\
+                               block 0 0 0 grass\nblock 1 0 0 dirt\nblock 2 0 0 stone\n\
+                               block 3 0 0 lava\nblock 0 1 0 wood\nblock 1 1 0 tall_grass\n\
+                               block 0 2 0 leaves\nchunk 0 0 -1\nblock 0 0 15 dirt\n";
+
+    fn load(text: &str) -> (VoxelWorld, Vec<String>) {
+        let file = WorldFile::parse(text).unwrap();
+        let (blocks, unknown) = file.resolve_blocks(BlockType::from_file_name, BlockType::Stone);
+        let world = VoxelWorld {
+            blocks: blocks.into_iter().collect(),
+            ..default()
+        };
+        (world, unknown)
+    }
+
+    #[test]
+    fn opus_export_loads_with_unknown_blocks_as_stone() {
+        let (world, unknown) = load(OPUS_EXPORT);
+        assert_eq!(unknown, ["lava", "tall_grass"]);
+        assert_eq!(world.blocks[&IVec3::new(0, 0, 0)], BlockType::Grass);
+        assert_eq!(world.blocks[&IVec3::new(0, 2, 0)], BlockType::Leaves);
+        assert_eq!(world.blocks[&IVec3::new(3, 0, 0)], BlockType::Stone);
+        assert_eq!(world.blocks[&IVec3::new(1, 1, 0)], BlockType::Stone);
+        assert_eq!(world.blocks[&IVec3::new(0, 0, -1)], BlockType::Dirt);
+    }
+
+    #[test]
+    fn import_report_names_the_blocks_loaded_as_stone() {
+        let file = WorldFile::parse(OPUS_EXPORT).unwrap();
+        let (_, unknown) = file.resolve_blocks(BlockType::from_file_name, BlockType::Stone);
+        let message = import_message(&file, &unknown);
+        assert!(
+            message.starts_with("Imported 8 blocks from opus"),
+            "{message}"
+        );
+        assert!(
+            message.ends_with("loaded as stone: lava, tall_grass"),
+            "{message}"
+        );
+        assert!(!import_message(&file, &[]).contains("stone"));
+    }
+
+    /// Opus to Gemini and back keeps every block both games have where it
+    /// was, and the player where they stood.
+    #[test]
+    fn shared_blocks_and_player_survive_a_round_trip() {
+        let (world, _) = load(OPUS_EXPORT);
+        let opus = WorldFile::parse(OPUS_EXPORT).unwrap();
+        let half_height = CollisionBox::PLAYER.half_height;
+        let translation = player_translation(&opus, half_height);
+
+        let written = world_file_from(
+            &world,
+            translation,
+            half_height,
+            opus.player.health,
+            opus.player.hunger,
+        )
+        .serialize();
+        let (reloaded, _) = load(&written);
+        assert_eq!(reloaded.blocks, world.blocks);
+        let back = WorldFile::parse(&written).unwrap();
+        assert_eq!(back.player, opus.player);
+        assert_eq!(back.player.hunger, 35.5);
+        for (coord, name) in &opus.blocks {
+            if BlockType::from_file_name(name).is_some() {
+                assert!(back.blocks.contains(&(*coord, name.clone())), "{name}");
+            }
+        }
+    }
+
+    /// Opus's player at (0.5, 1, 0.5) stands on top of block (0, 0, 0);
+    /// Gemini draws that block centred on the origin, so its top is at 0.5.
+    #[test]
+    fn player_lands_on_the_same_block() {
+        let opus = WorldFile::parse(OPUS_EXPORT).unwrap();
+        let half_height = CollisionBox::PLAYER.half_height;
+        let translation = player_translation(&opus, half_height);
+        assert_eq!(translation.xz(), Vec2::ZERO);
+        assert!((translation.y - half_height - 0.5).abs() < 1e-6);
+    }
+}
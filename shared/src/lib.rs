@@ -0,0 +1,293 @@
+//! World file shared by Opus and Gemini, so a world built in either game
+//! opens in the other. Both games depend on this crate, so the format
+//! only lives here; bump
+//! `WORLD_FILE_VERSION` when the layout changes.
+//!
+//! ```text
+//! voxel-world 3
+//! source <game>
+//! player <x> <y> <z>
+//! health <health>
+//! hunger <hunger>
+//! chunk <cx> <cy> <cz>
+//! block <x> <y> <z> <name>
+//! ```
+//!
+//! Blocks are grouped into `WORLD_FILE_CHUNK_SIZE` chunks: each `chunk`
+//! line starts a chunk, and the `block` lines after it give positions
+//! within that chunk, so block `x y z` under chunk `cx cy cz` is at
+//! `(cx, cy, cz) * WORLD_FILE_CHUNK_SIZE + (x, y, z)`. Block lines before
+//! any chunk line are in world coordinates, which is how version 1 and 2
+//! files wrote every block.
+//!
+//! Coordinates: block `x y z` is the unit cell from (x, y, z) to
+//! (x + 1, y + 1, z + 1), and `player` is the centre of the bottom of the
+//! player's collision box in the same space. Opus's physics already works
+//! this way (it draws each cube centred on its cell's corner, but collides
+//! with the cell). Gemini centres blocks on integer coordinates, so it
+//! shifts positions by half a block on the way in and out; block indices
+//! are the same in both.
+//!
+//! Block names are lowercase with underscores (`grass`, `tall_grass`).
+//! Lines of an unknown kind are skipped, so later versions can add metadata
+//! without breaking older readers. Files without `health` or `hunger`
+//! lines load the player at full health and fed. Version 1 files stored
+//! each game's raw player translation; they still load, with the player
+//! slightly off.
+
+use bevy_math::{IVec3, Vec3};
+use std::path::PathBuf;
+
+pub const WORLD_FILE_VERSION: u32 = 3;
+pub const WORLD_FILE_CHUNK_SIZE: i32 = 16;
+const WORLD_FILE_MAGIC: &str = "voxel-world";
+/// Next to both games' directories. Found from this crate's manifest, so
+/// it's the same file whichever directory a game is run from.
+const WORLD_FILE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../shared_world.txt");
+/// Overrides where the world file lives.
+const WORLD_FILE_ENV: &str = "VOXEL_WORLD_FILE";
+
+/// `$VOXEL_WORLD_FILE` if set, otherwise WORLD_FILE_PATH.
+pub fn world_file_path() -> PathBuf {
+    std::env::var_os(WORLD_FILE_ENV).map_or_else(|| PathBuf::from(WORLD_FILE_PATH), PathBuf::from)
+}
+
+/// The player as both games have them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerState {
+    /// Feet position, see the module docs for the coordinate space.
+    pub feet: Vec3,
+    pub health: f32,
+    pub hunger: f32,
+}
+
+impl PlayerState {
+    /// Standing at `feet`, at full health and fed.
+    pub fn at(feet: Vec3) -> Self {
+        Self {
+            feet,
+            health: 100.0,
+            hunger: 100.0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WorldFile {
+    /// Game that wrote the file.
+    pub source: String,
+    pub player: PlayerState,
+    /// World coordinates, grouped by chunk when written.
+    pub blocks: Vec<(IVec3, String)>,
+}
+
+/// The file chunk holding `coord`, and where in it the block sits.
+fn split_chunk(coord: IVec3) -> (IVec3, IVec3) {
+    let size = IVec3::splat(WORLD_FILE_CHUNK_SIZE);
+    (coord.div_euclid(size), coord.rem_euclid(size))
+}
+
+impl WorldFile {
+    /// A file from `source` with `blocks` sorted chunk by chunk, bottom
+    /// layer first in each, so the same world always writes the same file.
+    pub fn new(source: &str, player: PlayerState, mut blocks: Vec<(IVec3, String)>) -> Self {
+        blocks.sort_by_key(|(coord, _)| {
+            let (chunk, local) = split_chunk(*coord);
+            (chunk.y, chunk.z, chunk.x, local.y, local.z, local.x)
+        });
+        Self {
+            source: source.to_string(),
+            player,
+            blocks,
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut lines = contents.lines();
+        let header = lines.next().unwrap_or_default();
+        let version = header
+            .strip_prefix(WORLD_FILE_MAGIC)
+            .and_then(|rest| rest.trim().parse::<u32>().ok())
+            .ok_or_else(|| format!("not a world file (header {header:?})"))?;
+        if version > WORLD_FILE_VERSION {
+            return Err(format!(
+                "world file version {version} is newer than this game's {WORLD_FILE_VERSION}"
+            ));
+        }
+
+        let mut source = String::new();
+        let mut player = PlayerState::at(Vec3::ZERO);
+        let mut chunk_origin = IVec3::ZERO;
+        let mut blocks = Vec::new();
+        for (number, line) in lines.enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let bad_line = || format!("line {}: {line:?}", number + 2);
+            match fields.as_slice() {
+                ["source", name] => source = name.to_string(),
+                ["player", x, y, z] => {
+                    let coords: Result<Vec<f32>, _> = [x, y, z].iter().map(|v| v.parse()).collect();
+                    let coords = coords.map_err(|_| bad_line())?;
+                    player.feet = Vec3::new(coords[0], coords[1], coords[2]);
+                }
+                ["health", value] => player.health = value.parse().map_err(|_| bad_line())?,
+                ["hunger", value] => player.hunger = value.parse().map_err(|_| bad_line())?,
+                ["chunk", x, y, z] => {
+                    let coords: Result<Vec<i32>, _> = [x, y, z].iter().map(|v| v.parse()).collect();
+                    let coords = coords.map_err(|_| bad_line())?;
+                    chunk_origin =
+                        IVec3::new(coords[0], coords[1], coords[2]) * WORLD_FILE_CHUNK_SIZE;
+                }
+                ["block", x, y, z, name] => {
+                    let coords: Result<Vec<i32>, _> = [x, y, z].iter().map(|v| v.parse()).collect();
+                    let coords = coords.map_err(|_| bad_line())?;
+                    blocks.push((
+                        chunk_origin + IVec3::new(coords[0], coords[1], coords[2]),
+                        name.to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            source,
+            player,
+            blocks,
+        })
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = format!(
+            "{WORLD_FILE_MAGIC} {WORLD_FILE_VERSION}\nsource {}\n",
+            self.source
+        );
+        let p = self.player;
+        out.push_str(&format!("player {} {} {}\n", p.feet.x, p.feet.y, p.feet.z));
+        out.push_str(&format!("health {}\nhunger {}\n", p.health, p.hunger));
+        let mut current_chunk = None;
+        for (coord, name) in &self.blocks {
+            let (chunk, local) = split_chunk(*coord);
+            if current_chunk != Some(chunk) {
+                out.push_str(&format!("chunk {} {} {}\n", chunk.x, chunk.y, chunk.z));
+                current_chunk = Some(chunk);
+            }
+            out.push_str(&format!(
+                "block {} {} {} {name}\n",
+                local.x, local.y, local.z
+            ));
+        }
+        out
+    }
+
+    /// The blocks as one game's block type, looked up by name. Names the
+    /// game doesn't know become `fallback` and are listed once each in the
+    /// second half, for the warning after import.
+    pub fn resolve_blocks<B: Copy>(
+        &self,
+        from_name: impl Fn(&str) -> Option<B>,
+        fallback: B,
+    ) -> (Vec<(IVec3, B)>, Vec<String>) {
+        let mut unknown: Vec<String> = Vec::new();
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|(coord, name)| {
+                let block_type = from_name(name).unwrap_or_else(|| {
+                    if !unknown.contains(name) {
+                        unknown.push(name.clone());
+                    }
+                    fallback
+                });
+                (*coord, block_type)
+            })
+            .collect();
+        (blocks, unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> WorldFile {
+        WorldFile::new(
+            "test",
+            PlayerState {
+                feet: Vec3::new(0.5, 3.0, -1.25),
+                health: 62.5,
+                hunger: 40.0,
+            },
+            vec![
+                (IVec3::new(1, 2, 3), "grass".to_string()),
+                (IVec3::new(-4, 0, 7), "tall_grass".to_string()),
+                (IVec3::new(0, -1, 0), "stone".to_string()),
+            ],
+        )
+    }
+
+    #[test]
+    fn world_file_round_trips_through_text() {
+        let file = sample();
+        let text = file.serialize();
+        let parsed = WorldFile::parse(&text).unwrap();
+        assert_eq!(parsed, file);
+        assert_eq!(parsed.serialize(), text);
+    }
+
+    #[test]
+    fn blocks_are_written_bottom_layer_first() {
+        let ys: Vec<i32> = sample().blocks.iter().map(|(coord, _)| coord.y).collect();
+        assert_eq!(ys, [-1, 0, 2]);
+    }
+
+    #[test]
+    fn blocks_are_grouped_into_chunks() {
+        let text = sample().serialize();
+        let layout: Vec<&str> = text.lines().skip(5).collect();
+        assert_eq!(
+            layout,
+            [
+                "chunk 0 -1 0",
+                "block 0 15 0 stone",
+                "chunk -1 0 0",
+                "block 12 0 7 tall_grass",
+                "chunk 0 0 0",
+                "block 1 2 3 grass",
+            ]
+        );
+    }
+
+    #[test]
+    fn newer_versions_and_other_files_are_refused() {
+        let newer = format!("{WORLD_FILE_MAGIC} {}\n", WORLD_FILE_VERSION + 1);
+        assert!(WorldFile::parse(&newer).is_err());
+        assert!(WorldFile::parse("hello\n").is_err());
+        assert!(WorldFile::parse("voxel-world 1\nblock 1 x 3 dirt\n").is_err());
+    }
+
+    #[test]
+    fn version_one_files_and_unknown_lines_still_load() {
+        let file = WorldFile::parse(
+            "voxel-world 1\nsource gemini\nweather rain\nplayer 1 2 3\nblock 0 0 0 dirt\n",
+        )
+        .unwrap();
+        assert_eq!(file.source, "gemini");
+        assert_eq!(file.player, PlayerState::at(Vec3::new(1.0, 2.0, 3.0)));
+        assert_eq!(file.blocks, [(IVec3::ZERO, "dirt".to_string())]);
+    }
+
+    #[test]
+    fn unknown_block_names_fall_back_and_are_listed_once() {
+        let file = WorldFile::new(
+            "test",
+            PlayerState::at(Vec3::ZERO),
+            vec![
+                (IVec3::new(0, 0, 0), "dirt".to_string()),
+                (IVec3::new(1, 0, 0), "lava".to_string()),
+                (IVec3::new(2, 0, 0), "lava".to_string()),
+            ],
+        );
+        let (blocks, unknown) = file.resolve_blocks(|name| (name == "dirt").then_some('d'), 's');
+        assert_eq!(blocks.iter().map(|&(_, b)| b).collect::<String>(), "dss");
+        assert_eq!(unknown, ["lava"]);
+    }
+}